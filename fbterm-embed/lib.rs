@@ -0,0 +1,348 @@
+//! Build script helper that pre-rasterizes PSF, BDF and TrueType fonts into
+//! Rust source for a `static` `fbterm::BitmapFont`, so targets without `alloc`
+//! can still use large bitmap fonts.
+//!
+//! ```ignore
+//! // build.rs
+//! fbterm_embed::Embed::psf("fonts/ter-u32n.psf")?
+//!     .subset(&['\u{20}'..='\u{7e}', '\u{2500}'..='\u{257f}'])
+//!     .write()?;
+//!
+//! // src/main.rs
+//! static FONT: fbterm::BitmapFont = fbterm::include_font!("ter-u32n.psf");
+//!
+//! let mut term = fbterm::Fbterm::new(framebuffer, FONT);
+//! ```
+
+use fbterm::{BdfFont, Font, Point, TrueTypeFont};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{Error, ErrorKind, Result};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+/// A set of fixed size 1 bit per pixel glyphs ready to be written out
+pub struct Embed {
+    name: String,
+    width: usize,
+    height: usize,
+    glyphs: Vec<Vec<u8>>,
+    table: BTreeMap<char, usize>,
+}
+
+impl Embed {
+    /// Load a PC Screen Font (version 1 or 2), using its unicode table if present
+    pub fn psf<P: AsRef<Path>>(path: P) -> Result<Embed> {
+        let data = std::fs::read(&path)?;
+        let (width, height, count, has_table, offset) = psf_header(&data)?;
+        let size = width
+            .div_ceil(8)
+            .checked_mul(height)
+            .filter(|&size| size > 0)
+            .ok_or_else(|| invalid("PSF glyph size is invalid"))?;
+        let end = count
+            .checked_mul(size)
+            .and_then(|len| len.checked_add(offset))
+            .ok_or_else(|| invalid("PSF glyph data is too large"))?;
+        if data.len() < end {
+            return Err(invalid("PSF glyph data is truncated"));
+        }
+        let glyphs = data[offset..end].chunks(size).map(<[u8]>::to_vec).collect();
+        let table = if has_table {
+            psf_table(&data[end..], count, data[0] == 0x36)
+        } else {
+            (0..count)
+                .filter_map(|i| Some((char::from_u32(i as u32)?, i)))
+                .collect()
+        };
+        Ok(Embed {
+            name: file_name(&path),
+            width,
+            height,
+            glyphs,
+            table,
+        })
+    }
+
+    /// Load a BDF font, aligning every glyph in a cell as wide as its bounding box
+    pub fn bdf<P: AsRef<Path>>(path: P) -> Result<Embed> {
+        let data = std::fs::read(&path)?;
+        let mut font = BdfFont::parse(&data).map_err(|e| invalid(&format!("{:?}", e)))?;
+        let chars = font.chars().collect::<Vec<_>>();
+        Ok(Embed::from_font(&mut font, &chars, file_name(&path)))
+    }
+
+    /// Rasterize `ranges` of a TrueType font at `px` pixels, coverage of at
+    /// least 50% becoming a set pixel
+    pub fn truetype<P: AsRef<Path>>(
+        path: P,
+        px: f32,
+        ranges: &[RangeInclusive<char>],
+    ) -> Result<Embed> {
+        let data = std::fs::read(&path)?;
+        let mut font = TrueTypeFont::new(&data, px).map_err(|e| invalid(&format!("{:?}", e)))?;
+        let chars = ranges.iter().cloned().flatten().collect::<Vec<_>>();
+        Ok(Embed::from_font(&mut font, &chars, file_name(&path)))
+    }
+
+    /// Rasterize `chars` of any `Font` into cells of its `cell_width`,
+    /// clipping wide glyphs
+    pub fn from_font<F: Font>(font: &mut F, chars: &[char], name: String) -> Embed {
+        let height = font.height();
+        let width = font.cell_width();
+        let stride = width.div_ceil(8);
+        let mut embed = Embed {
+            name,
+            width,
+            height,
+            glyphs: Vec::new(),
+            table: BTreeMap::new(),
+        };
+        for &c in chars {
+            let glyph = match font.get_glyph(c) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            let mut cell = vec![0u8; stride * height];
+            for y in 0..glyph.height {
+                for x in 0..glyph.width {
+                    let cx = glyph.x + x;
+                    let cy = glyph.y + y as isize;
+                    if cx >= width || cy < 0 || cy as usize >= height {
+                        continue;
+                    }
+                    let set = match font.get_pixel(&glyph, x, y) {
+                        Point::Bit(bit) => bit,
+                        Point::Coverage(cov) => cov >= 128,
+                        Point::SubpixelCoverage(r, g, b) => r as u16 + g as u16 + b as u16 >= 384,
+                    };
+                    if set {
+                        cell[cy as usize * stride + cx / 8] |= 0x80 >> (cx % 8);
+                    }
+                }
+            }
+            embed.table.insert(c, embed.glyphs.len());
+            embed.glyphs.push(cell);
+        }
+        embed
+    }
+
+    /// Keep only the codepoints inside `ranges`, dropping unused glyphs
+    pub fn subset(mut self, ranges: &[RangeInclusive<char>]) -> Embed {
+        self.table
+            .retain(|c, _| ranges.iter().any(|r| r.contains(c)));
+        let mut remap = BTreeMap::new();
+        let mut glyphs = Vec::new();
+        let old = std::mem::take(&mut self.glyphs);
+        for index in self.table.values_mut() {
+            *index = *remap.entry(*index).or_insert_with(|| {
+                glyphs.push(old[*index].clone());
+                glyphs.len() - 1
+            });
+        }
+        self.glyphs = glyphs;
+        self
+    }
+
+    /// File name under `OUT_DIR` read back by `include_font!`, defaults to the
+    /// name of the source font file
+    pub fn rename(mut self, name: &str) -> Embed {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Write `$OUT_DIR/<name>.rs`
+    pub fn write(&self) -> Result<PathBuf> {
+        let out_dir = std::env::var_os("OUT_DIR")
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "OUT_DIR is not set"))?;
+        let path = Path::new(&out_dir).join(format!("{}.rs", self.name));
+        std::fs::write(&path, self.to_source())?;
+        Ok(path)
+    }
+
+    /// Rust expression building the `BitmapFont`, expects `BitmapFont` in scope
+    pub fn to_source(&self) -> String {
+        let mut src = String::new();
+        writeln!(src, "BitmapFont::new(").unwrap();
+        writeln!(src, "    &[").unwrap();
+        for row in self.glyphs.concat().chunks(16) {
+            src.push_str("       ");
+            for byte in row {
+                write!(src, " 0x{:02x},", byte).unwrap();
+            }
+            src.push('\n');
+        }
+        writeln!(src, "    ],").unwrap();
+        writeln!(src, "    {},", self.width).unwrap();
+        writeln!(src, "    {},", self.height).unwrap();
+        writeln!(src, ")").unwrap();
+        writeln!(src, ".with_table(&[").unwrap();
+        for (c, index) in &self.table {
+            writeln!(src, "    ('\\u{{{:x}}}', {}),", *c as u32, index).unwrap();
+        }
+        writeln!(src, "])").unwrap();
+        src
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+fn file_name<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref()
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn le32(data: &[u8], offset: usize) -> Result<usize> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| invalid("PSF header is truncated"))
+}
+
+/// (width, height, glyph count, has unicode table, glyph data offset)
+fn psf_header(data: &[u8]) -> Result<(usize, usize, usize, bool, usize)> {
+    match data {
+        [0x36, 0x04, mode, size, ..] => {
+            let count = if mode & 0x01 != 0 { 512 } else { 256 };
+            Ok((8, *size as usize, count, mode & 0x06 != 0, 4))
+        }
+        [0x72, 0xb5, 0x4a, 0x86, ..] => {
+            let offset = le32(data, 8)?;
+            let has_table = le32(data, 12)? & 0x01 != 0;
+            let count = le32(data, 16)?;
+            let height = le32(data, 24)?;
+            let width = le32(data, 28)?;
+            Ok((width, height, count, has_table, offset))
+        }
+        _ => Err(invalid("not a PSF font")),
+    }
+}
+
+/// Parse the unicode table following the glyphs. Multi-codepoint sequences are skipped.
+fn psf_table(data: &[u8], count: usize, psf1: bool) -> BTreeMap<char, usize> {
+    let mut table = BTreeMap::new();
+    let mut rest = data;
+    for index in 0..count {
+        if psf1 {
+            let mut sequence = false;
+            while let [lo, hi, tail @ ..] = rest {
+                rest = tail;
+                match u16::from_le_bytes([*lo, *hi]) {
+                    0xffff => break,
+                    0xfffe => sequence = true,
+                    code if !sequence => {
+                        if let Some(c) = char::from_u32(code as u32) {
+                            table.entry(c).or_insert(index);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        } else {
+            let end = rest.iter().position(|&b| b == 0xff).unwrap_or(rest.len());
+            let entry = &rest[..end];
+            rest = rest.get(end + 1..).unwrap_or(&[]);
+            let singles = entry.split(|&b| b == 0xfe).next().unwrap_or(&[]);
+            for c in String::from_utf8_lossy(singles).chars() {
+                if c != char::REPLACEMENT_CHARACTER {
+                    table.entry(c).or_insert(index);
+                }
+            }
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PSF2 font of two 8x2 glyphs, `A` for glyph 0 and `B` plus a skipped
+    /// sequence for glyph 1
+    fn psf2() -> Vec<u8> {
+        let mut data = vec![0x72, 0xb5, 0x4a, 0x86];
+        for field in [0u32, 32, 1, 2, 2, 2, 8] {
+            data.extend(field.to_le_bytes());
+        }
+        data.extend([0x81, 0x42, 0x24, 0x18]);
+        data.extend(b"A\xffB\xfexy\xff");
+        data
+    }
+
+    /// `Embed::psf` of `data`, written to a temporary file
+    fn load(data: &[u8]) -> Result<Embed> {
+        let path = std::env::temp_dir().join(format!("fbterm-embed-{}.psf", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        let embed = Embed::psf(&path);
+        std::fs::remove_file(&path).unwrap();
+        embed
+    }
+
+    #[test]
+    fn parse_psf2() {
+        let embed = load(&psf2()).unwrap();
+        assert_eq!((embed.width, embed.height), (8, 2));
+        assert_eq!(embed.glyphs, [vec![0x81, 0x42], vec![0x24, 0x18]]);
+        assert_eq!(
+            embed.table.into_iter().collect::<Vec<_>>(),
+            [('A', 0), ('B', 1)]
+        );
+    }
+
+    #[test]
+    fn reject_truncated_psf() {
+        let data = psf2();
+        assert!(psf_header(&data[..20]).is_err());
+        assert!(psf_header(b"not a font").is_err());
+    }
+
+    #[test]
+    fn reject_overflowing_psf_header() {
+        for (field, value) in [
+            (16, u32::MAX),
+            (24, u32::MAX),
+            (28, u32::MAX),
+            (8, u32::MAX),
+            (24, 0),
+        ] {
+            let mut data = psf2();
+            data[field..field + 4].copy_from_slice(&value.to_le_bytes());
+            let error = load(&data).err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    /// The tokens of a source expression, and the value it evaluates to
+    macro_rules! source {
+        ($($tokens:tt)*) => {
+            (stringify!($($tokens)*), $($tokens)*)
+        };
+    }
+
+    #[test]
+    fn source_builds_the_font() {
+        use fbterm::BitmapFont;
+        let (expected, mut font): (_, BitmapFont) = source! {
+            BitmapFont::new(
+                &[
+                    0x81, 0x42, 0x24, 0x18,
+                ],
+                8,
+                2,
+            )
+            .with_table(&[
+                ('\u{41}', 0),
+                ('\u{42}', 1),
+            ])
+        };
+        let tokens = |s: &str| s.split_whitespace().collect::<String>();
+        let embed = load(&psf2()).unwrap();
+        assert_eq!(tokens(&embed.to_source()), tokens(expected));
+        let glyph = font.get_glyph('B').unwrap();
+        let row = (0..8).map(|x| matches!(font.get_pixel(&glyph, x, 1), Point::Bit(true)));
+        assert!(row.eq([false, false, false, true, true, false, false, false]));
+    }
+}
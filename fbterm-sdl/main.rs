@@ -1,6 +1,5 @@
 use clap::{value_t, App, Arg};
 use fbterm::*;
-use sdl2;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::{Color, PixelFormatEnum};
@@ -47,7 +46,7 @@ fn main() {
             let mut buf = Vec::new();
             file.read_to_end(&mut buf).expect("Can't read file");
             println!("Load font file: {}", path);
-            let font = TrueTypeFont::new(&buf, size);
+            let font = TrueTypeFont::new(&buf, size).expect("Can't load font");
             println!("load font done");
             run(width, height, font)
         }
@@ -90,22 +89,19 @@ fn run<F: Font>(width: usize, height: usize, font: F) {
     let mut double_buffer = vec![0u8; 4 * width * height];
     let background = RGBA8888::new(0, 0, 0xA8, 0);
     let foreground = RGBA8888::new(0xA8, 0xA8, 0xA8, 255);
-    let fb = unsafe {
-        Framebuffer::new(
-            std::ptr::NonNull::new((&mut frame_buffer).as_mut_ptr()).expect("fb is null"),
-            width,
-            height,
-            width,
-            background,
-            foreground,
-        )
-    };
+    let fb = Framebuffer::from_slice(
+        &mut frame_buffer,
+        width,
+        height,
+        4 * width,
+        background,
+        foreground,
+    )
+    .expect("Invalid framebuffer");
     let mut term = Fbterm::new(fb, font);
-    unsafe {
-        term.framebuffer.set_double_buffer(
-            std::ptr::NonNull::new((&mut double_buffer).as_mut_ptr()).expect("fb is null"),
-        )
-    };
+    term.framebuffer
+        .set_back_buffer(&mut double_buffer)
+        .expect("Invalid double buffer");
     term.clear();
     let mut texture = texture_creator
         .create_texture_streaming(PixelFormatEnum::RGBA8888, width as u32, height as u32)
@@ -121,8 +117,10 @@ Any characters you type will be displayed on the screen.
 Turn of IME before input on Windows.
 本程序支持中文显示",
     );
-    println!("{:?}", term.lines());
-    texture.update(None, &frame_buffer, 4 * width).unwrap();
+    print_lines(&term);
+    texture
+        .update(None, term.framebuffer.front(), 4 * width)
+        .unwrap();
     canvas.clear();
     canvas.copy(&texture, None, None).unwrap();
     canvas.present();
@@ -136,11 +134,13 @@ Turn of IME before input on Windows.
                 } => break,
                 Event::TextInput { text, .. } => {
                     term.print(&text);
-                    texture.update(None, &frame_buffer, 4 * width).unwrap();
+                    texture
+                        .update(None, term.framebuffer.front(), 4 * width)
+                        .unwrap();
                     canvas.clear();
                     canvas.copy(&texture, None, None).unwrap();
                     canvas.present();
-                    println!("{:?}", term.lines());
+                    print_lines(&term);
                 }
                 Event::KeyDown {
                     keycode: Some(key), ..
@@ -151,11 +151,13 @@ Turn of IME before input on Windows.
                         _ => {}
                     }
                     term.flush();
-                    texture.update(None, &frame_buffer, 4 * width).unwrap();
+                    texture
+                        .update(None, term.framebuffer.front(), 4 * width)
+                        .unwrap();
                     canvas.clear();
                     canvas.copy(&texture, None, None).unwrap();
                     canvas.present();
-                    println!("{:?}", term.lines());
+                    print_lines(&term);
                 }
                 _ => {
                     canvas.clear();
@@ -166,3 +168,12 @@ Turn of IME before input on Windows.
         };
     }
 }
+
+fn print_lines<F: Font>(term: &Fbterm<RGBA8888, F>) {
+    let lines = term
+        .lines()
+        .iter()
+        .map(|line| line.iter().map(|cell| cell.c).collect::<String>())
+        .collect::<Vec<_>>();
+    println!("{:?}", lines);
+}
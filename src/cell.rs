@@ -0,0 +1,80 @@
+use crate::{Pixel, Style};
+
+/// A character on screen with the colors and style it was drawn with
+#[derive(Debug, Copy, Clone)]
+pub struct Cell<P: Pixel> {
+    pub c: char,
+    pub foreground: P,
+    pub background: P,
+    pub style: Style,
+}
+
+/// Characters printed without `alloc`, in storage lent by the caller, so they can
+/// be drawn again with their colors and style. Lines end with a `'\n'` cell and the
+/// oldest cells are overwritten once it is full.
+#[cfg(not(feature = "alloc"))]
+pub(crate) struct CellRing<'a, P: Pixel> {
+    cells: &'a mut [Cell<P>],
+    start: usize,
+    len: usize,
+}
+
+#[cfg(not(feature = "alloc"))]
+impl<'a, P: Pixel> CellRing<'a, P> {
+    pub(crate) fn new(cells: &'a mut [Cell<P>]) -> CellRing<'a, P> {
+        CellRing {
+            cells,
+            start: 0,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn capacity(&self) -> usize {
+        self.cells.len()
+    }
+
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The `i`th oldest cell
+    #[inline]
+    pub(crate) fn get(&self, i: usize) -> Cell<P> {
+        self.cells[(self.start + i) % self.capacity()]
+    }
+
+    pub(crate) fn push(&mut self, cell: Cell<P>) {
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return;
+        }
+        self.cells[(self.start + self.len) % capacity] = cell;
+        if self.len == capacity {
+            self.start = (self.start + 1) % capacity;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// Remove the last cell of the last line
+    pub(crate) fn pop(&mut self) -> Option<Cell<P>> {
+        let last = self.get(self.len.checked_sub(1)?);
+        if last.c == '\n' {
+            return None;
+        }
+        self.len -= 1;
+        Some(last)
+    }
+
+    #[inline]
+    pub(crate) fn clear_line(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.len = 0
+    }
+}
@@ -0,0 +1,105 @@
+use crate::fb::Rect;
+
+/// Number of rects a `Damage` keeps apart before merging the closest ones
+pub const DAMAGE_RECTS: usize = 8;
+
+/// Areas of the screen drawn since the last flush.
+///
+/// Rects are merged when covering both costs no more than covering each, e.g.
+/// neighbouring cells of a line, so typing in one corner and updating a clock in
+/// another only flushes those two areas. Once full, a new rect is merged with the
+/// one it grows the least.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Damage {
+    rects: [Rect; DAMAGE_RECTS],
+    len: usize,
+}
+
+impl Damage {
+    pub fn new() -> Damage {
+        Damage::default()
+    }
+
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+        let mut rect = rect;
+        while let Some(i) = self
+            .rects()
+            .iter()
+            .position(|old| old.union(&rect).area() <= old.area() + rect.area())
+        {
+            rect = rect.union(&self.remove(i));
+        }
+        if self.len == DAMAGE_RECTS {
+            let i = (0..self.len)
+                .min_by_key(|&i| self.rects[i].union(&rect).area() - self.rects[i].area())
+                .unwrap_or(0);
+            rect = rect.union(&self.remove(i));
+        }
+        self.rects[self.len] = rect;
+        self.len += 1;
+    }
+
+    fn remove(&mut self, i: usize) -> Rect {
+        let rect = self.rects[i];
+        self.len -= 1;
+        self.rects[i] = self.rects[self.len];
+        rect
+    }
+
+    #[inline]
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects[..self.len]
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.len = 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbouring_cells_merge() {
+        let mut damage = Damage::new();
+        damage.add(Rect::new(0, 0, 8, 16));
+        damage.add(Rect::new(8, 0, 8, 16));
+        damage.add(Rect::new(4, 4, 2, 2));
+        assert_eq!(damage.rects(), [Rect::new(0, 0, 16, 16)]);
+    }
+
+    #[test]
+    fn opposite_corners_stay_apart() {
+        let mut damage = Damage::new();
+        damage.add(Rect::new(0, 0, 8, 16));
+        damage.add(Rect::new(632, 464, 8, 16));
+        damage.add(Rect::new(0, 0, 0, 16));
+        assert_eq!(
+            damage.rects(),
+            [Rect::new(0, 0, 8, 16), Rect::new(632, 464, 8, 16)]
+        );
+    }
+
+    #[test]
+    fn full_list_merges_where_it_grows_least() {
+        let mut damage = Damage::new();
+        for i in 0..DAMAGE_RECTS {
+            damage.add(Rect::new(i * 100, 0, 10, 10));
+        }
+        assert_eq!(damage.rects().len(), DAMAGE_RECTS);
+        damage.add(Rect::new(200, 30, 10, 10));
+        assert_eq!(damage.rects().len(), DAMAGE_RECTS);
+        assert!(damage.rects().contains(&Rect::new(200, 0, 10, 40)));
+        assert!(!damage.rects().contains(&Rect::new(200, 0, 10, 10)));
+    }
+}
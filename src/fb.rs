@@ -1,43 +1,195 @@
+use crate::damage::Damage;
+use crate::font::bitmap::BitOrder;
+use crate::format::PixelFormat;
+use crate::gamma;
+use crate::palette::{Palette, PaletteHook};
 use crate::pixel::*;
 use core::marker::PhantomData;
+use core::ops::Range;
 use core::ptr::NonNull;
+
+/// Error creating or drawing on a `Framebuffer`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FbError {
+    /// The width or the height is 0
+    Empty,
+    /// The pitch is smaller than a row of pixels
+    PitchTooSmall,
+    /// The buffer is smaller than `pitch * height` bytes
+    BufferTooSmall,
+    /// The size of the buffer overflows `usize`
+    Overflow,
+    /// The pixel or rect lies entirely outside the framebuffer
+    OutOfBounds,
+    /// The source and target of a copy differ in size
+    SizeMismatch,
+    /// The buffer or the pitch is not a multiple of the alignment of the pixel type
+    Misaligned,
+}
+
 pub struct Framebuffer<'a, T: Pixel> {
     base: NonNull<u8>,
     buffer: Option<NonNull<u8>>,
     width: usize,
     height: usize,
-    stride: usize,
+    /// Bytes from the start of a row to the start of the next
+    pitch: usize,
+    format: Option<PixelFormat>,
     background: T,
     foreground: T,
-    _lifetime: PhantomData<&'a u8>,
+    blending: Blending,
+    bit_order: BitOrder,
+    clip: Option<Rect>,
+    palette: Option<Palette>,
+    /// Palette entries blending the foreground over the background in 16 steps
+    ramp: Option<[T; 16]>,
+    _lifetime: PhantomData<&'a mut [u8]>,
 }
 
 impl<'a, T: Pixel> Framebuffer<'a, T> {
+    /// Framebuffer of `len` bytes at `base`, rows of `width` pixels starting
+    /// every `pitch` bytes
+    ///
+    /// # Safety
+    /// `base` must point to `len` bytes that stay valid and unaliased for `'a`
     pub unsafe fn new(
         base: NonNull<u8>,
+        len: usize,
+        width: usize,
+        height: usize,
+        pitch: usize,
+        background: T,
+        foreground: T,
+    ) -> Result<Framebuffer<'a, T>, FbError> {
+        validate(len, width, height, pitch, T::bits())?;
+        check_align::<T>(base, pitch)?;
+        Ok(Framebuffer::build(
+            base, width, height, pitch, None, background, foreground,
+        ))
+    }
+
+    /// Framebuffer borrowing `buffer`, rows of `width` pixels starting every `pitch` bytes
+    pub fn from_slice(
+        buffer: &'a mut [u8],
+        width: usize,
+        height: usize,
+        pitch: usize,
+        background: T,
+        foreground: T,
+    ) -> Result<Framebuffer<'a, T>, FbError> {
+        let len = buffer.len();
+        let base = NonNull::from(buffer).cast();
+        unsafe { Framebuffer::new(base, len, width, height, pitch, background, foreground) }
+    }
+
+    /// Framebuffer borrowing `pixels`, rows of `width` pixels starting every
+    /// `stride` pixels. `T` must be at least a byte wide.
+    pub fn from_pixels(
+        pixels: &'a mut [T],
         width: usize,
         height: usize,
         stride: usize,
         background: T,
         foreground: T,
+    ) -> Result<Framebuffer<'a, T>, FbError> {
+        let size = core::mem::size_of::<T>();
+        let pitch = stride.checked_mul(size).ok_or(FbError::Overflow)?;
+        let len = core::mem::size_of_val(pixels);
+        let base = NonNull::from(pixels).cast();
+        unsafe { Framebuffer::new(base, len, width, height, pitch, background, foreground) }
+    }
+
+    /// Framebuffer whose pixels are laid out as `format`, the colors given as `T`
+    /// (e.g. `RGBA8888`) being packed when written
+    ///
+    /// # Safety
+    /// `base` must point to `len` bytes that stay valid and unaliased for `'a`
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn with_format(
+        base: NonNull<u8>,
+        len: usize,
+        width: usize,
+        height: usize,
+        pitch: usize,
+        format: PixelFormat,
+        background: T,
+        foreground: T,
+    ) -> Result<Framebuffer<'a, T>, FbError> {
+        validate(len, width, height, pitch, format.bytes_per_pixel() * 8)?;
+        Ok(Framebuffer::build(
+            base,
+            width,
+            height,
+            pitch,
+            Some(format),
+            background,
+            foreground,
+        ))
+    }
+
+    fn build(
+        base: NonNull<u8>,
+        width: usize,
+        height: usize,
+        pitch: usize,
+        format: Option<PixelFormat>,
+        background: T,
+        foreground: T,
     ) -> Framebuffer<'a, T> {
-        Framebuffer {
+        let mut fb = Framebuffer {
             base,
             buffer: None,
             width,
             height,
-            stride,
+            pitch,
+            format,
             background,
             foreground,
+            blending: Blending::default(),
+            bit_order: BitOrder::MsbFirst,
+            clip: None,
+            palette: None,
+            ramp: None,
             _lifetime: PhantomData,
-        }
+        };
+        fb.update_ramp();
+        fb
     }
 
-    pub unsafe fn set_double_buffer(&mut self, buffer: NonNull<u8>) {
-        let real_buffer = self.base;
+    /// Draw on a back buffer of `len` bytes at `buffer`, copied to the
+    /// framebuffer by `flush`
+    ///
+    /// # Safety
+    /// `buffer` must point to `len` bytes that stay valid and unaliased for `'a`
+    pub unsafe fn set_double_buffer(
+        &mut self,
+        buffer: NonNull<u8>,
+        len: usize,
+    ) -> Result<(), FbError> {
+        if len < self.buffer_size() {
+            return Err(FbError::BufferTooSmall);
+        }
+        if self.format.is_none() {
+            check_align::<T>(buffer, self.pitch)?;
+        }
+        // a back buffer set before is replaced, the framebuffer staying the front
+        self.buffer = Some(self.buffer.unwrap_or(self.base));
         self.base = buffer;
-        self.buffer = Some(real_buffer);
-        self.clear()
+        self.clear();
+        Ok(())
+    }
+
+    /// Draw on `buffer`, copied to the framebuffer by `flush`, replacing the back
+    /// buffer set before if any
+    pub fn set_back_buffer(&mut self, buffer: &'a mut [u8]) -> Result<(), FbError> {
+        let len = buffer.len();
+        unsafe { self.set_double_buffer(NonNull::from(buffer).cast(), len) }
+    }
+
+    /// Bytes shown on screen, read from the front buffer
+    pub fn front(&self) -> &[u8] {
+        let base = self.buffer.unwrap_or(self.base);
+        unsafe { core::slice::from_raw_parts(base.as_ptr(), self.buffer_size()) }
     }
 
     pub fn flush(&mut self, rect: Option<Rect>) {
@@ -52,47 +204,219 @@ impl<'a, T: Pixel> Framebuffer<'a, T> {
             rect
         );
         for y in rect.y..(rect.y + rect.height) {
-            let index = (y * self.stride + rect.x) * T::size();
+            let bytes = self.bytes(rect.x, y, rect.width);
             unsafe {
                 core::ptr::copy_nonoverlapping(
-                    self.base.as_ptr().add(index),
-                    real_buffer.as_ptr().add(index),
-                    rect.width * T::size(),
+                    self.base.as_ptr().add(bytes.start),
+                    real_buffer.as_ptr().add(bytes.start),
+                    bytes.len(),
                 );
             }
         }
     }
 
+    /// Flush every rect of `damage`, clipped to the framebuffer, and clear it
+    pub fn flush_damage(&mut self, damage: &mut Damage) {
+        for &rect in damage.rects() {
+            self.try_flush(Some(rect)).ok();
+        }
+        damage.clear()
+    }
+
+    /// Bytes per pixel in memory, 1 for pixels packed several to a byte
+    #[inline]
+    pub fn pixel_size(&self) -> usize {
+        match &self.format {
+            Some(format) => format.bytes_per_pixel(),
+            None => T::size(),
+        }
+    }
+
+    /// Bits per pixel in memory
+    #[inline]
+    pub fn pixel_bits(&self) -> usize {
+        match &self.format {
+            Some(format) => format.bytes_per_pixel() * 8,
+            None => T::bits(),
+        }
+    }
+
+    /// Whether several pixels share a byte
     #[inline]
-    pub unsafe fn read(&self, index: usize) -> T {
-        T::read_volatile(self.base.as_ptr().add(index * T::size()))
+    fn packed(&self) -> bool {
+        self.pixel_bits() < 8
     }
 
     #[inline]
-    pub unsafe fn write(&mut self, index: usize, val: T) {
-        val.write_volatile(self.base.as_ptr().add(index * T::size()))
+    pub fn get_bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// Order of the pixels packed in a byte, `MsbFirst` by default
+    #[inline]
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order
+    }
+
+    /// Bytes holding the `count` pixels from (`x`, `y`)
+    #[inline]
+    fn bytes(&self, x: usize, y: usize, count: usize) -> Range<usize> {
+        let bits = self.pixel_bits();
+        let row = y * self.pitch;
+        row + x * bits / 8..row + ((x + count) * bits).div_ceil(8)
+    }
+
+    /// Byte holding packed pixel (`x`, `y`), and the shift of the pixel in it
+    #[inline]
+    fn locate(&self, x: usize, y: usize) -> (usize, usize) {
+        let bits = self.pixel_bits();
+        let offset = x * bits;
+        let shift = match self.bit_order {
+            BitOrder::MsbFirst => 8 - bits - offset % 8,
+            BitOrder::LsbFirst => offset % 8,
+        };
+        (y * self.pitch + offset / 8, shift)
+    }
+
+    /// Split the `count` packed pixels from (`x`, `y`) into the columns before
+    /// the first whole byte, the whole bytes, and the columns after them
+    fn split(
+        &self,
+        x: usize,
+        y: usize,
+        count: usize,
+    ) -> (Range<usize>, Range<usize>, Range<usize>) {
+        let per_byte = 8 / self.pixel_bits();
+        let end = x + count;
+        let first = x.div_ceil(per_byte) * per_byte;
+        let last = end / per_byte * per_byte;
+        if first >= last {
+            return (x..end, 0..0, end..end);
+        }
+        let row = y * self.pitch;
+        (
+            x..first,
+            row + first / per_byte..row + last / per_byte,
+            last..end,
+        )
+    }
+
+    /// Byte of packed pixels all set to `pixel`
+    fn fill_byte(&self, pixel: T) -> u8 {
+        let bits = self.pixel_bits();
+        let mut value = 0u8;
+        unsafe { pixel.write_volatile(&mut value) };
+        let value = value & ((1u16 << bits) - 1) as u8;
+        (0..8 / bits).fold(0, |byte, i| byte | value << (i * bits))
+    }
+
+    #[inline]
+    pub fn get_format(&self) -> Option<PixelFormat> {
+        self.format
+    }
+
+    /// Pixel (`x`, `y`), ignoring the clip rect
+    ///
+    /// # Safety
+    /// (`x`, `y`) must be inside the framebuffer
+    #[inline]
+    pub unsafe fn read(&self, x: usize, y: usize) -> T {
+        if self.packed() {
+            let (byte, shift) = self.locate(x, y);
+            let mut value = self.base.as_ptr().add(byte).read_volatile() >> shift;
+            return T::read_volatile(&mut value);
+        }
+        let ptr = self.base.as_ptr().add(self.bytes(x, y, 0).start);
+        match &self.format {
+            Some(format) => {
+                let (r, g, b, a) = format.unpack(format.read(ptr));
+                T::new(r, g, b, a)
+            }
+            None => T::read_volatile(ptr),
+        }
+    }
+
+    /// Set pixel (`x`, `y`), ignoring the clip rect
+    ///
+    /// # Safety
+    /// (`x`, `y`) must be inside the framebuffer
+    #[inline]
+    pub unsafe fn write(&mut self, x: usize, y: usize, val: T) {
+        if self.packed() {
+            let mut value = 0u8;
+            val.write_volatile(&mut value);
+            let (byte, shift) = self.locate(x, y);
+            let mask = (((1u16 << self.pixel_bits()) - 1) << shift) as u8;
+            let ptr = self.base.as_ptr().add(byte);
+            ptr.write_volatile(ptr.read_volatile() & !mask | (value << shift) & mask);
+            return;
+        }
+        let ptr = self.base.as_ptr().add(self.bytes(x, y, 0).start);
+        match &self.format {
+            Some(format) => format.write(ptr, format.pack(val.get())),
+            None => val.write_volatile(ptr),
+        }
     }
 
     #[inline]
     pub fn get_pixel(&self, x: usize, y: usize) -> T {
         assert!(x < self.width, "Frame buffer accessed out of bounds");
         assert!(y < self.height, "Frame buffer accessed out of bounds");
-        unsafe { self.read(y * self.stride + x) }
+        unsafe { self.read(x, y) }
     }
 
+    /// Set pixel (`x`, `y`) if it is inside the clip rect
+    ///
+    /// # Safety
+    /// (`x`, `y`) must be inside the framebuffer
     #[inline]
     pub unsafe fn draw_pixel(&mut self, x: usize, y: usize, pixel: T) {
-        self.write(y * self.stride + x, pixel)
+        if self.visible(x, y) {
+            self.write(x, y, pixel)
+        }
     }
 
-    pub fn clear(&mut self) {
-        for i in 0..self.height * self.stride {
-            unsafe { self.write(i, self.background) }
+    #[inline]
+    pub fn get_clip(&self) -> Option<Rect> {
+        self.clip
+    }
+
+    /// Only draw inside `clip`, e.g. for a terminal in a window. `clear`
+    /// only clears the clip rect.
+    ///
+    /// The clip rect is kept by the framebuffer rather than passed to every call,
+    /// so that `Fbterm` and other code drawing through it stay inside it. Every
+    /// drawing call is clipped to it, the `try_` variants returning
+    /// `FbError::OutOfBounds` when nothing is left to draw. Reads (`get_pixel`,
+    /// `front`, the source of `copy_rect`) and `flush` ignore it.
+    #[inline]
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip
+    }
+
+    #[inline]
+    fn visible(&self, x: usize, y: usize) -> bool {
+        match &self.clip {
+            Some(clip) => clip.contains(x, y),
+            None => true,
         }
     }
 
+    /// Part of `rect` inside the clip rect
+    #[inline]
+    fn clipped(&self, rect: Rect) -> Option<Rect> {
+        match &self.clip {
+            Some(clip) => rect.intersection(clip),
+            None => Some(rect).filter(|rect| !rect.is_empty()),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.draw_rect(Rect::new(0, 0, self.width, self.height), self.background)
+    }
+
+    /// Copy the pixels of `src` to `dst`, which may overlap
     pub fn copy_rect(&mut self, src: Rect, dst: Rect) {
-        assert!(src.top() != dst.top(), "can't copy to self"); // copy_nonoverlapping
         assert_eq!(
             src.width, dst.width,
             "The width of the source and target are different: src: {:?} != dst: {:?}",
@@ -113,18 +437,84 @@ impl<'a, T: Pixel> Framebuffer<'a, T> {
             "target Rect is out of bounds: {:?}",
             dst
         );
-        for y in 0..src.height {
-            unsafe {
-                core::ptr::copy_nonoverlapping(
-                    self.base
-                        .as_ptr()
-                        .add(((src.y + y) * self.stride + src.x) * T::size()),
-                    self.base
-                        .as_ptr()
-                        .add(((dst.y + y) * self.stride + dst.x) * T::size()),
-                    src.width * T::size(),
-                );
-            }
+        let visible = match self.clipped(dst) {
+            Some(visible) => visible,
+            None => return,
+        };
+        let src = Rect::new(
+            src.x + visible.x - dst.x,
+            src.y + visible.y - dst.y,
+            visible.width,
+            visible.height,
+        );
+        let dst = visible;
+        let base = self.base.as_ptr();
+        let row = self.bytes(0, 0, src.width);
+        if !self.packed() && src.x == 0 && dst.x == 0 && row.len() == self.pitch {
+            // whole rows are contiguous
+            let (from, to) = (src.y * self.pitch, dst.y * self.pitch);
+            unsafe { core::ptr::copy(base.add(from), base.add(to), src.height * self.pitch) };
+            return;
+        }
+        // rows moving down are copied from the bottom so they are read before being overwritten
+        let rows = (0..src.height).map(|y| if dst.y > src.y { src.height - 1 - y } else { y });
+        for y in rows {
+            unsafe { self.copy_row((src.x, src.y + y), (dst.x, dst.y + y), src.width) }
+        }
+    }
+
+    /// Copy `count` pixels of a row from `from` to `to`, which may be the same
+    /// row, packed pixels being copied a byte at a time when both start at the same bit
+    unsafe fn copy_row(&mut self, from: (usize, usize), to: (usize, usize), count: usize) {
+        let base = self.base.as_ptr();
+        if !self.packed() {
+            let (src, dst) = (self.bytes(from.0, from.1, count), self.bytes(to.0, to.1, 0));
+            core::ptr::copy(base.add(src.start), base.add(dst.start), src.len());
+            return;
+        }
+        // pixels moving right are copied from the right
+        let right = to.0 > from.0;
+        let per_byte = 8 / self.pixel_bits();
+        if from.0 % per_byte != to.0 % per_byte {
+            self.copy_pixels(from, to, 0..count, right);
+            return;
+        }
+        let (head, bytes, tail) = self.split(to.0, to.1, count);
+        if bytes.is_empty() {
+            self.copy_pixels(from, to, 0..count, right);
+            return;
+        }
+        let (head, tail) = (
+            head.start - to.0..head.end - to.0,
+            tail.start - to.0..tail.end - to.0,
+        );
+        let start =
+            bytes.start - self.bytes(to.0, to.1, 0).start + self.bytes(from.0, from.1, 0).start;
+        if right {
+            self.copy_pixels(from, to, tail, right);
+            core::ptr::copy(base.add(start), base.add(bytes.start), bytes.len());
+            self.copy_pixels(from, to, head, right);
+        } else {
+            self.copy_pixels(from, to, head, right);
+            core::ptr::copy(base.add(start), base.add(bytes.start), bytes.len());
+            self.copy_pixels(from, to, tail, right);
+        }
+    }
+
+    /// Copy pixels `from + i` to `to + i` for every `i` in `range`, from the
+    /// last one if `reverse`
+    unsafe fn copy_pixels(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+        range: Range<usize>,
+        reverse: bool,
+    ) {
+        let mut copy = |i| self.write(to.0 + i, to.1, self.read(from.0 + i, from.1));
+        if reverse {
+            range.rev().for_each(&mut copy)
+        } else {
+            range.for_each(&mut copy)
         }
     }
 
@@ -134,41 +524,88 @@ impl<'a, T: Pixel> Framebuffer<'a, T> {
             "target Rect is out of bounds: {:?}",
             dst
         );
-        for y in 0..dst.height {
-            for x in 0..dst.width {
-                unsafe {
-                    self.write((dst.y + y) * self.stride + dst.x + x, pixel);
+        let dst = match self.clipped(dst) {
+            Some(dst) => dst,
+            None => return,
+        };
+        if self.packed() {
+            let fill = self.fill_byte(pixel);
+            for y in dst.y..dst.bottom() {
+                let (head, bytes, tail) = self.split(dst.x, y, dst.width);
+                for x in head.chain(tail) {
+                    unsafe { self.write(x, y, pixel) }
+                }
+                for i in bytes {
+                    unsafe { self.base.as_ptr().add(i).write_volatile(fill) }
                 }
             }
+            return;
+        }
+        for y in dst.y..dst.bottom() {
+            for x in dst.x..dst.right() {
+                unsafe { self.write(x, y, pixel) }
+            }
         }
     }
 
+    /// Blend the foreground over the background with coverage `alpha`
+    ///
+    /// # Safety
+    /// (`x`, `y`) must be inside the framebuffer
     #[inline]
     pub unsafe fn draw_alpha(&mut self, x: usize, y: usize, alpha: u8) {
-        let fg = self.foreground.get();
-        let bg = self.background.get();
+        let alpha = self.blending.coverage(alpha);
         let pixel = if alpha == 0 {
             self.background
         } else if alpha == 255 {
             self.foreground
+        } else if let Some(ramp) = &self.ramp {
+            ramp[(alpha as usize + 8) / 17]
         } else {
-            let map = |f: u8, b: u8, a: u8| {
-                let f = f as i32;
-                let b = b as i32;
-                let diff = f - b;
-                let add = (diff * (a as i32)) / 256i32;
-                (b + add) as u8
-            };
+            let fg = self.foreground.get();
+            let bg = self.background.get();
             T::new(
-                map(fg.0, bg.0, alpha),
-                map(fg.1, bg.1, alpha),
-                map(fg.2, bg.2, alpha),
-                map(fg.3, bg.3, alpha),
+                self.blending.mix(fg.0, bg.0, alpha),
+                self.blending.mix(fg.1, bg.1, alpha),
+                self.blending.mix(fg.2, bg.2, alpha),
+                mix(fg.3, bg.3, alpha),
             )
         };
         self.draw_pixel(x, y, pixel)
     }
 
+    /// Blend the foreground over the background with a coverage per color
+    /// channel, for LCD subpixel antialiasing
+    ///
+    /// # Safety
+    /// (`x`, `y`) must be inside the framebuffer
+    #[inline]
+    pub unsafe fn draw_subpixel(&mut self, x: usize, y: usize, r: u8, g: u8, b: u8) {
+        if r == g && g == b || self.ramp.is_some() {
+            let alpha = (r as u16 + g as u16 + b as u16) / 3;
+            return self.draw_alpha(x, y, alpha as u8);
+        }
+        let fg = self.foreground.get();
+        let bg = self.background.get();
+        let (r, g, b) = (
+            self.blending.coverage(r),
+            self.blending.coverage(g),
+            self.blending.coverage(b),
+        );
+        let alpha = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+        let pixel = T::new(
+            self.blending.mix(fg.0, bg.0, r),
+            self.blending.mix(fg.1, bg.1, g),
+            self.blending.mix(fg.2, bg.2, b),
+            mix(fg.3, bg.3, alpha),
+        );
+        self.draw_pixel(x, y, pixel)
+    }
+
+    /// Draw the foreground if `bit` is set, the background otherwise
+    ///
+    /// # Safety
+    /// (`x`, `y`) must be inside the framebuffer
     #[inline]
     pub unsafe fn draw_bit(&mut self, x: usize, y: usize, bit: bool) {
         if bit {
@@ -178,6 +615,164 @@ impl<'a, T: Pixel> Framebuffer<'a, T> {
         }
     }
 
+    /// Draw `count` (up to 8) pixels rightwards from (`x`, `y`), the most
+    /// significant of `bits` being the leftmost. A 1 bit framebuffer is written a
+    /// whole byte at once when the pixels fill one.
+    ///
+    /// # Safety
+    /// The `count` pixels must be inside the framebuffer
+    pub unsafe fn draw_bits(&mut self, x: usize, y: usize, bits: u8, count: usize) {
+        let whole = self.clipped(Rect::new(x, y, 8, 1)) == Some(Rect::new(x, y, 8, 1));
+        if count == 8 && self.pixel_bits() == 1 && x.is_multiple_of(8) && whole {
+            let bits = match self.bit_order {
+                BitOrder::MsbFirst => bits,
+                BitOrder::LsbFirst => bits.reverse_bits(),
+            };
+            let byte =
+                bits & self.fill_byte(self.foreground) | !bits & self.fill_byte(self.background);
+            self.base
+                .as_ptr()
+                .add(y * self.pitch + x / 8)
+                .write_volatile(byte);
+            return;
+        }
+        for i in 0..count {
+            self.draw_bit(x + i, y, bits & (0x80 >> i) != 0)
+        }
+    }
+
+    /// Part of `rect` inside the framebuffer, `None` if `rect` is empty
+    fn clip(&self, rect: Rect) -> Result<Option<Rect>, FbError> {
+        if rect.is_empty() {
+            return Ok(None);
+        }
+        let screen = Rect::new(0, 0, self.width, self.height);
+        rect.intersection(&screen)
+            .ok_or(FbError::OutOfBounds)
+            .map(Some)
+    }
+
+    /// Part of `rect` drawn on, inside the framebuffer and the clip rect, `None`
+    /// if `rect` is empty
+    fn clip_draw(&self, rect: Rect) -> Result<Option<Rect>, FbError> {
+        match self.clip(rect)? {
+            Some(rect) => self.clipped(rect).ok_or(FbError::OutOfBounds).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    fn check(&self, x: usize, y: usize) -> Result<(), FbError> {
+        if x < self.width && y < self.height {
+            Ok(())
+        } else {
+            Err(FbError::OutOfBounds)
+        }
+    }
+
+    /// `check` for a pixel drawn on, which must also be inside the clip rect
+    #[inline]
+    fn check_draw(&self, x: usize, y: usize) -> Result<(), FbError> {
+        self.check(x, y)?;
+        if self.visible(x, y) {
+            Ok(())
+        } else {
+            Err(FbError::OutOfBounds)
+        }
+    }
+
+    /// `flush` clipping `rect` to the framebuffer
+    pub fn try_flush(&mut self, rect: Option<Rect>) -> Result<(), FbError> {
+        let rect = match rect {
+            Some(rect) => match self.clip(rect)? {
+                Some(rect) => Some(rect),
+                None => return Ok(()),
+            },
+            None => None,
+        };
+        self.flush(rect);
+        Ok(())
+    }
+
+    pub fn try_get_pixel(&self, x: usize, y: usize) -> Result<T, FbError> {
+        self.check(x, y)?;
+        Ok(unsafe { self.read(x, y) })
+    }
+
+    pub fn try_draw_pixel(&mut self, x: usize, y: usize, pixel: T) -> Result<(), FbError> {
+        self.check_draw(x, y)?;
+        unsafe { self.draw_pixel(x, y, pixel) };
+        Ok(())
+    }
+
+    pub fn try_draw_alpha(&mut self, x: usize, y: usize, alpha: u8) -> Result<(), FbError> {
+        self.check_draw(x, y)?;
+        unsafe { self.draw_alpha(x, y, alpha) };
+        Ok(())
+    }
+
+    pub fn try_draw_subpixel(
+        &mut self,
+        x: usize,
+        y: usize,
+        r: u8,
+        g: u8,
+        b: u8,
+    ) -> Result<(), FbError> {
+        self.check_draw(x, y)?;
+        unsafe { self.draw_subpixel(x, y, r, g, b) };
+        Ok(())
+    }
+
+    pub fn try_draw_bit(&mut self, x: usize, y: usize, bit: bool) -> Result<(), FbError> {
+        self.check_draw(x, y)?;
+        unsafe { self.draw_bit(x, y, bit) };
+        Ok(())
+    }
+
+    /// `draw_bits` dropping the pixels past the right edge
+    pub fn try_draw_bits(
+        &mut self,
+        x: usize,
+        y: usize,
+        bits: u8,
+        count: usize,
+    ) -> Result<(), FbError> {
+        self.check(x, y)?;
+        let count = count.min(8).min(self.width - x);
+        if self.clip_draw(Rect::new(x, y, count, 1))?.is_some() {
+            unsafe { self.draw_bits(x, y, bits, count) };
+        }
+        Ok(())
+    }
+
+    /// `draw_rect` clipping `dst` to the framebuffer
+    pub fn try_draw_rect(&mut self, dst: Rect, pixel: T) -> Result<(), FbError> {
+        if let Some(dst) = self.clip_draw(dst)? {
+            self.draw_rect(dst, pixel);
+        }
+        Ok(())
+    }
+
+    /// `copy_rect` clipping the source and the target to the framebuffer
+    pub fn try_copy_rect(&mut self, src: Rect, dst: Rect) -> Result<(), FbError> {
+        if src.width != dst.width || src.height != dst.height {
+            return Err(FbError::SizeMismatch);
+        }
+        let (src_clip, dst_clip) = match (self.clip(src)?, self.clip(dst)?) {
+            (Some(src), Some(dst)) => (src, dst),
+            _ => return Ok(()),
+        };
+        self.clip_draw(dst_clip)?;
+        let width = src_clip.width.min(dst_clip.width);
+        let height = src_clip.height.min(dst_clip.height);
+        self.copy_rect(
+            Rect::new(src.x, src.y, width, height),
+            Rect::new(dst.x, dst.y, width, height),
+        );
+        Ok(())
+    }
+
     #[inline]
     pub fn get_foreground(&self) -> T {
         self.foreground
@@ -185,7 +780,11 @@ impl<'a, T: Pixel> Framebuffer<'a, T> {
 
     #[inline]
     pub fn set_foreground(&mut self, pixel: T) {
-        self.foreground = pixel
+        let unchanged = same_index(pixel, self.foreground);
+        self.foreground = pixel;
+        if !unchanged {
+            self.update_ramp()
+        }
     }
 
     #[inline]
@@ -195,7 +794,81 @@ impl<'a, T: Pixel> Framebuffer<'a, T> {
 
     #[inline]
     pub fn set_background(&mut self, pixel: T) {
-        self.background = pixel
+        let unchanged = same_index(pixel, self.background);
+        self.background = pixel;
+        if !unchanged {
+            self.update_ramp()
+        }
+    }
+
+    #[inline]
+    pub fn get_blending(&self) -> Blending {
+        self.blending
+    }
+
+    #[inline]
+    pub fn set_blending(&mut self, blending: Blending) {
+        if blending == self.blending {
+            return;
+        }
+        self.blending = blending;
+        self.update_ramp()
+    }
+
+    /// Palette of indexed color pixels, `None` meaning the xterm palette
+    #[inline]
+    pub fn get_palette(&self) -> Option<&Palette> {
+        self.palette.as_ref()
+    }
+
+    /// Colors the palette entries of indexed color pixels (`Indexed`) are displayed with
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = Some(palette);
+        self.update_ramp()
+    }
+
+    /// Load the palette into the display hardware
+    pub fn load_palette<H: PaletteHook>(&self, hook: &mut H) {
+        hook.load(self.palette.as_ref().unwrap_or(&XTERM))
+    }
+
+    /// Use the palette the display hardware is currently set to
+    pub fn read_palette<H: PaletteHook>(&mut self, hook: &mut H) {
+        self.set_palette(hook.read())
+    }
+
+    /// Pixel of a color, the closest palette entry for indexed color pixels
+    pub fn color(&self, r: u8, g: u8, b: u8) -> T {
+        match &self.palette {
+            Some(palette) => T::from_index(palette.nearest(r, g, b)),
+            None => None,
+        }
+        .unwrap_or_else(|| T::new(r, g, b, 255))
+    }
+
+    /// Precompute the blend table of indexed color pixels
+    fn update_ramp(&mut self) {
+        let (fg, bg) = match (self.foreground.index(), self.background.index()) {
+            (Some(fg), Some(bg)) => (fg, bg),
+            _ => {
+                self.ramp = None;
+                return;
+            }
+        };
+        let palette = self.palette.as_ref().unwrap_or(&XTERM);
+        let (fg, bg) = (palette.get(fg), palette.get(bg));
+        let mut ramp = [self.background; 16];
+        for (i, pixel) in ramp.iter_mut().enumerate().skip(1) {
+            let alpha = i as u8 * 17;
+            let index = palette.nearest(
+                self.blending.mix(fg.0, bg.0, alpha),
+                self.blending.mix(fg.1, bg.1, alpha),
+                self.blending.mix(fg.2, bg.2, alpha),
+            );
+            *pixel = T::from_index(index).unwrap_or(self.background);
+        }
+        ramp[15] = self.foreground;
+        self.ramp = Some(ramp)
     }
 
     #[inline]
@@ -208,9 +881,15 @@ impl<'a, T: Pixel> Framebuffer<'a, T> {
         self.height
     }
 
+    #[inline]
+    pub fn pitch(&self) -> usize {
+        self.pitch
+    }
+
+    /// Bytes a back buffer needs
     #[inline]
     pub fn buffer_size(&self) -> usize {
-        self.height * self.stride * T::size()
+        self.height * self.pitch
     }
 
     #[inline]
@@ -219,7 +898,93 @@ impl<'a, T: Pixel> Framebuffer<'a, T> {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+const XTERM: Palette = Palette::xterm();
+
+/// Whether `a` and `b` are the same palette entry, the blend table built for one
+/// fitting the other
+#[inline]
+fn same_index<T: Pixel>(a: T, b: T) -> bool {
+    a.index().is_some() && a.index() == b.index()
+}
+
+fn validate(
+    len: usize,
+    width: usize,
+    height: usize,
+    pitch: usize,
+    bits: usize,
+) -> Result<(), FbError> {
+    if width == 0 || height == 0 {
+        return Err(FbError::Empty);
+    }
+    let row = width
+        .checked_mul(bits)
+        .ok_or(FbError::Overflow)?
+        .div_ceil(8);
+    if pitch < row {
+        return Err(FbError::PitchTooSmall);
+    }
+    if len < pitch.checked_mul(height).ok_or(FbError::Overflow)? {
+        return Err(FbError::BufferTooSmall);
+    }
+    Ok(())
+}
+
+/// Check that pixels of `T` accessed at `base` and every `pitch` bytes after it are aligned
+fn check_align<T: Pixel>(base: NonNull<u8>, pitch: usize) -> Result<(), FbError> {
+    let align = T::align();
+    if !(base.as_ptr() as usize).is_multiple_of(align) || !pitch.is_multiple_of(align) {
+        return Err(FbError::Misaligned);
+    }
+    Ok(())
+}
+
+/// How antialiased pixels are blended between the background and the foreground.
+/// Coverage 0 and 255 always give exactly the background and the foreground.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Blending {
+    /// Blend in linear light rather than on sRGB values, so text keeps the same
+    /// weight on dark and light backgrounds. The sRGB curve is fixed, `contrast`
+    /// being what tunes the weight of text.
+    pub linear: bool,
+    /// Coverage added to partially covered pixels, from 0 (none) to 255, making thin text bolder
+    pub contrast: u8,
+}
+
+impl Default for Blending {
+    fn default() -> Blending {
+        Blending {
+            linear: true,
+            contrast: 0,
+        }
+    }
+}
+
+impl Blending {
+    #[inline]
+    fn coverage(&self, alpha: u8) -> u8 {
+        let alpha = alpha as u32;
+        (alpha + self.contrast as u32 * alpha * (255 - alpha) / (255 * 255)) as u8
+    }
+
+    #[inline]
+    fn mix(&self, fg: u8, bg: u8, alpha: u8) -> u8 {
+        if self.linear {
+            gamma::mix(fg, bg, alpha)
+        } else {
+            mix(fg, bg, alpha)
+        }
+    }
+}
+
+/// Mix `alpha / 255` of `fg` with the rest of `bg`
+#[inline]
+fn mix(fg: u8, bg: u8, alpha: u8) -> u8 {
+    let (fg, bg, alpha) = (fg as u32, bg as u32, alpha as u32);
+    ((fg * alpha + bg * (255 - alpha) + 127) / 255) as u8
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct Rect {
     x: usize,
     y: usize,
@@ -238,9 +1003,30 @@ impl Rect {
         }
     }
 
+    #[inline]
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    #[inline]
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// `x + width`, saturating at `usize::MAX`
     #[inline]
     pub fn right(&self) -> usize {
-        self.x + self.width
+        self.x.saturating_add(self.width)
     }
 
     #[inline]
@@ -253,8 +1039,307 @@ impl Rect {
         self.y
     }
 
+    /// `y + height`, saturating at `usize::MAX`
     #[inline]
     pub fn bottom(&self) -> usize {
-        self.y + self.height
+        self.y.saturating_add(self.height)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    #[inline]
+    pub fn area(&self) -> usize {
+        self.width.saturating_mul(self.height)
+    }
+
+    #[inline]
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.right()).contains(&x) && (self.y..self.bottom()).contains(&y)
+    }
+
+    /// Area covered by both rects, `None` if they don't overlap
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        if x >= right || y >= bottom {
+            return None;
+        }
+        Some(Rect::new(x, y, right - x, bottom - y))
+    }
+
+    /// Smallest rect covering both rects, empty rects being ignored
+    pub fn union(&self, other: &Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+        Rect::new(x, y, right - x, bottom - y)
+    }
+
+    /// The rect moved by (`dx`, `dy`), `None` if it would move past the top or left edge
+    pub fn translate(&self, dx: isize, dy: isize) -> Option<Rect> {
+        Some(Rect::new(
+            self.x.checked_add_signed(dx)?,
+            self.y.checked_add_signed(dy)?,
+            self.width,
+            self.height,
+        ))
+    }
+
+    /// The rect shrunk by `dx` on the left and right and `dy` on the top and bottom
+    pub fn inset(&self, dx: usize, dy: usize) -> Rect {
+        let width = self.width.saturating_sub(2 * dx);
+        let height = self.height.saturating_sub(2 * dy);
+        Rect::new(
+            self.x.saturating_add(dx.min(self.width / 2)),
+            self.y.saturating_add(dy.min(self.height / 2)),
+            width,
+            height,
+        )
+    }
+
+    /// The y coordinate of every row
+    #[inline]
+    pub fn rows(&self) -> Range<usize> {
+        self.y..self.bottom()
+    }
+
+    /// The x coordinate of every column
+    #[inline]
+    pub fn columns(&self) -> Range<usize> {
+        self.x..self.right()
+    }
+}
+
+/// Black on white `Gray8` framebuffer of `width * height` pixels over `buffer`
+#[cfg(test)]
+pub(crate) fn gray_fb(buffer: &mut [u8], width: usize, height: usize) -> Framebuffer<'_, Gray8> {
+    let (black, white) = (Gray8::new(0, 0, 0, 0), Gray8::new(255, 255, 255, 255));
+    Framebuffer::from_slice(buffer, width, height, width, black, white).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pixel relying on the default, aligned, `write_volatile` and `read_volatile`
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    struct Word(u32);
+
+    impl Pixel for Word {
+        fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+            Word(u32::from_le_bytes([r, g, b, a]))
+        }
+        fn get(&self) -> (u8, u8, u8, u8) {
+            let [r, g, b, a] = self.0.to_le_bytes();
+            (r, g, b, a)
+        }
+    }
+
+    /// Bytes of `words`, which are aligned for `Word`
+    fn bytes(words: &mut [u32]) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr().cast(), words.len() * 4) }
+    }
+
+    #[test]
+    fn empty_and_huge_rects_dont_panic() {
+        let mut buffer = [0u8; 16];
+        let white = Gray8::new(255, 255, 255, 255);
+        let mut fb = gray_fb(&mut buffer, 4, 4);
+        let mut back = [0u8; 16];
+        fb.set_back_buffer(&mut back).unwrap();
+        let empty = Rect::new(100, 100, 0, 5);
+        assert_eq!(fb.try_draw_rect(empty, white), Ok(()));
+        assert_eq!(fb.try_flush(Some(empty)), Ok(()));
+        assert_eq!(fb.try_copy_rect(empty, Rect::new(0, 0, 0, 5)), Ok(()));
+
+        let huge = Rect::new(usize::MAX, 0, 2, 1);
+        assert_eq!(huge.right(), usize::MAX);
+        assert_eq!(huge.intersection(&Rect::new(0, 0, 4, 4)), None);
+        assert_eq!(fb.try_draw_rect(huge, white), Err(FbError::OutOfBounds));
+        assert_eq!(fb.try_flush(Some(huge)), Err(FbError::OutOfBounds));
+        assert!(fb.front().iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn try_calls_apply_the_clip_rect() {
+        let mut buffer = [0u8; 16];
+        let white = Gray8::new(255, 255, 255, 255);
+        let mut fb = gray_fb(&mut buffer, 4, 4);
+        fb.set_clip(Some(Rect::new(1, 1, 2, 2)));
+        assert_eq!(fb.try_draw_pixel(0, 0, white), Err(FbError::OutOfBounds));
+        assert_eq!(fb.try_draw_bit(3, 3, true), Err(FbError::OutOfBounds));
+        assert_eq!(fb.try_draw_bits(0, 0, 0xff, 4), Err(FbError::OutOfBounds));
+        let outside = Rect::new(3, 0, 1, 4);
+        assert_eq!(fb.try_draw_rect(outside, white), Err(FbError::OutOfBounds));
+        assert_eq!(fb.try_draw_rect(Rect::new(0, 0, 2, 2), white), Ok(()));
+        assert_eq!(fb.try_draw_bits(0, 2, 0xff, 4), Ok(()));
+        assert_eq!(fb.try_get_pixel(0, 0).map(|p| p.0), Ok(0));
+        let drawn = fb.front().iter().map(|&p| p != 0).collect::<Vec<_>>();
+        assert_eq!(
+            drawn,
+            [
+                [false, false, false, false],
+                [false, true, false, false],
+                [false, true, true, false],
+                [false, false, false, false],
+            ]
+            .concat()
+        );
+    }
+
+    /// Copy rects of every size, overlapping or not, and compare with a copy of
+    /// the pixels read back one at a time
+    fn copies_match_reference<T: Pixel>() {
+        let (width, height) = (21, 5);
+        let pitch = (width * T::bits()).div_ceil(8) + 1;
+        let (black, white) = (T::new(0, 0, 0, 0), T::new(255, 255, 255, 255));
+        let mut buffer = vec![0u8; pitch * height];
+        let mut fb =
+            Framebuffer::from_slice(&mut buffer, width, height, pitch, black, white).unwrap();
+        for w in [1, 2, 3, 5, 8, 9, 17] {
+            for (sx, dx) in (0..5).flat_map(|sx| (0..5).map(move |dx| (sx, dx))) {
+                for (sy, dy) in [(0, 0), (1, 1), (0, 2), (2, 0), (1, 3)] {
+                    for h in [1, 2] {
+                        for y in 0..height {
+                            for x in 0..width {
+                                let v = ((x * 7 + y * 13) * 37 % 256) as u8;
+                                unsafe { fb.write(x, y, T::new(v, v, v, 255)) };
+                            }
+                        }
+                        let pixels = |fb: &Framebuffer<T>| {
+                            (0..height)
+                                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                                .map(|(x, y)| fb.get_pixel(x, y).get())
+                                .collect::<Vec<_>>()
+                        };
+                        let mut expected = pixels(&fb);
+                        let before = expected.clone();
+                        for (y, x) in (0..h).flat_map(|y| (0..w).map(move |x| (y, x))) {
+                            let from = (sy + y) * width + sx + x;
+                            expected[(dy + y) * width + dx + x] = before[from];
+                        }
+                        fb.copy_rect(Rect::new(sx, sy, w, h), Rect::new(dx, dy, w, h));
+                        assert_eq!(
+                            pixels(&fb),
+                            expected,
+                            "{} bits, {}x{} from ({}, {}) to ({}, {})",
+                            T::bits(),
+                            w,
+                            h,
+                            sx,
+                            sy,
+                            dx,
+                            dy
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn copy_rect_matches_reference() {
+        copies_match_reference::<Gray<1>>();
+        copies_match_reference::<Gray<2>>();
+        copies_match_reference::<Gray<4>>();
+        copies_match_reference::<Gray<8>>();
+        copies_match_reference::<RGBA8888>();
+        copies_match_reference::<BGR888>();
+        copies_match_reference::<RGB565>();
+    }
+
+    #[test]
+    fn second_back_buffer_keeps_the_front() {
+        let mut buffer = [0u8; 4];
+        let white = Gray8::new(255, 255, 255, 255);
+        let mut fb = gray_fb(&mut buffer, 2, 2);
+        let (mut first, mut second) = ([0u8; 4], [0u8; 4]);
+        fb.set_back_buffer(&mut first).unwrap();
+        fb.set_back_buffer(&mut second).unwrap();
+        fb.draw_rect(Rect::new(0, 0, 2, 1), white);
+        fb.flush(None);
+        assert_eq!(fb.front(), [255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn blending_endpoints_are_exact() {
+        let (fg, bg) = (
+            RGBA8888::new(200, 30, 7, 255),
+            RGBA8888::new(12, 250, 99, 128),
+        );
+        let rgb = |fb: &Framebuffer<RGBA8888>| {
+            let (r, g, b, _) = fb.get_pixel(0, 0).get();
+            (r, g, b)
+        };
+        for linear in [false, true] {
+            for contrast in [0, 128, 255] {
+                let mut buffer = [0u8; 4];
+                let mut fb = Framebuffer::from_slice(&mut buffer, 1, 1, 4, bg, fg).unwrap();
+                fb.set_blending(Blending { linear, contrast });
+                unsafe { fb.draw_alpha(0, 0, 0) };
+                assert_eq!(fb.get_pixel(0, 0).get(), bg.get());
+                unsafe { fb.draw_alpha(0, 0, 255) };
+                assert_eq!(fb.get_pixel(0, 0).get(), fg.get());
+                unsafe { fb.draw_subpixel(0, 0, 255, 0, 255) };
+                assert_eq!(rgb(&fb), (200, 250, 7));
+                unsafe { fb.draw_subpixel(0, 0, 0, 255, 0) };
+                assert_eq!(rgb(&fb), (12, 30, 99));
+            }
+        }
+    }
+
+    #[test]
+    fn ramp_follows_colors() {
+        let mut buffer = [0u8; 1];
+        let mut fb =
+            Framebuffer::from_slice(&mut buffer, 1, 1, 1, Indexed(0), Indexed(15)).unwrap();
+        fb.set_foreground(Indexed(15));
+        fb.set_background(Indexed(0));
+        unsafe { fb.draw_alpha(0, 0, 128) };
+        let gray = fb.front()[0];
+        assert!(gray != 0 && gray != 15);
+        fb.set_foreground(Indexed(9));
+        unsafe { fb.draw_alpha(0, 0, 255) };
+        assert_eq!(fb.front()[0], 9);
+        fb.set_blending(Blending {
+            linear: false,
+            contrast: 0,
+        });
+        unsafe { fb.draw_alpha(0, 0, 0) };
+        assert_eq!(fb.front()[0], 0);
+    }
+
+    #[test]
+    fn misaligned_buffers_are_rejected() {
+        let (black, white) = (Word::new(0, 0, 0, 0), Word::new(255, 255, 255, 255));
+        let mut words = [0u32; 9];
+        let buffer = &mut bytes(&mut words)[1..];
+        let fb = Framebuffer::from_slice(buffer, 2, 2, 8, black, white);
+        assert_eq!(fb.err(), Some(FbError::Misaligned));
+
+        let mut words = [0u32; 9];
+        let fb = Framebuffer::from_slice(bytes(&mut words), 2, 2, 9, black, white);
+        assert_eq!(fb.err(), Some(FbError::Misaligned));
+
+        let mut words = [0u32; 4];
+        let mut back = [0u32; 5];
+        let mut fb = Framebuffer::from_slice(bytes(&mut words), 2, 2, 8, black, white).unwrap();
+        let result = fb.set_back_buffer(&mut bytes(&mut back)[1..]);
+        assert_eq!(result, Err(FbError::Misaligned));
+
+        // pixels written a byte at a time need no alignment
+        let mut buffer = [0u8; 9];
+        let (black, white) = (RGB565::new(0, 0, 0, 0), RGB565::new(255, 255, 255, 255));
+        assert!(Framebuffer::from_slice(&mut buffer[1..], 2, 2, 4, black, white).is_ok());
     }
 }
@@ -0,0 +1,344 @@
+use super::{
+    bitmap::{bit, BitOrder},
+    Cow, Font, FontError, Glyph, Point,
+};
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use core::convert::TryFrom;
+
+/// Bitmap font loaded from the Glyph Bitmap Distribution Format (BDF)
+pub struct BdfFont {
+    glyphs: BTreeMap<char, Glyph>,
+    ascent: isize,
+    height: usize,
+    width: usize,
+}
+
+struct BoundingBox {
+    width: usize,
+    height: usize,
+    x: isize,
+    y: isize,
+}
+
+impl BdfFont {
+    pub fn parse(data: &[u8]) -> Result<BdfFont, FontError> {
+        let mut lines = data
+            .split(|&b| b == b'\n')
+            .map(|line| line.trim_ascii())
+            .enumerate()
+            .map(|(n, line)| (n + 1, line));
+        let mut bbox = None;
+        let mut ascent = None;
+        let mut descent = None;
+        let mut raw = Vec::new();
+        while let Some((n, line)) = lines.next() {
+            let mut words = line.split(|&b| b == b' ').filter(|w| !w.is_empty());
+            match words.next() {
+                Some(b"FONTBOUNDINGBOX") => bbox = Some(parse_bbx(n, words)?),
+                Some(b"FONT_ASCENT") => ascent = Some(parse_metric(n, words.next())?),
+                Some(b"FONT_DESCENT") => descent = Some(parse_metric(n, words.next())?),
+                Some(b"STARTCHAR") => {
+                    if let Some(glyph) = parse_char(&mut lines)? {
+                        raw.push(glyph);
+                    }
+                }
+                Some(b"ENDFONT") => break,
+                _ => {}
+            }
+        }
+        let bbox = bbox.ok_or(FontError::MissingBoundingBox)?;
+        let ascent = ascent.unwrap_or(bbox.height as isize + bbox.y);
+        let descent = descent.unwrap_or(-bbox.y);
+        let height = (ascent + descent).max(0) as usize;
+        // the bounding box fits the widest glyph (e.g. CJK in unifont), cells are as
+        // wide as `M` or `0`
+        let width = ['M', '0']
+            .iter()
+            .find_map(|&c| {
+                raw.iter()
+                    .find(|glyph| glyph.0 == c)
+                    .and_then(|glyph| glyph.1)
+            })
+            .unwrap_or(bbox.width);
+        let mut font = BdfFont {
+            glyphs: BTreeMap::new(),
+            ascent,
+            height,
+            width,
+        };
+        for (c, advance, bbx, bitmap) in raw {
+            let advance = advance.unwrap_or(bbox.width);
+            let glyph = font.place(advance, bbx, &bitmap);
+            font.glyphs.insert(c, glyph);
+        }
+        Ok(font)
+    }
+
+    /// Add the glyphs of `other` for every codepoint this font doesn't cover yet,
+    /// re-aligned on this font's baseline
+    pub fn merge(&mut self, other: BdfFont) {
+        let shift = self.ascent - other.ascent;
+        for (c, glyph) in other.glyphs {
+            if self.glyphs.contains_key(&c) {
+                continue;
+            }
+            let stride = glyph.width.div_ceil(8);
+            let top = glyph.y + shift;
+            let bbx = BoundingBox {
+                width: glyph.width,
+                height: glyph.height,
+                x: glyph.x as isize,
+                y: self.ascent - top - glyph.height as isize,
+            };
+            let bitmap = glyph.data.chunks(stride.max(1)).collect::<Vec<_>>();
+            let glyph = self.place(glyph.advance, bbx, &bitmap);
+            self.glyphs.insert(c, glyph);
+        }
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.glyphs.contains_key(&c)
+    }
+
+    /// Every codepoint covered by this font, in ascending order
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.glyphs.keys().copied()
+    }
+
+    /// Crop a glyph bitmap to the font's line box and convert its BDF offsets
+    /// to a `Glyph` positioned from the top left of the line, columns left of
+    /// the origin being cut off
+    fn place<R: AsRef<[u8]>>(&self, advance: usize, bbx: BoundingBox, bitmap: &[R]) -> Glyph {
+        let skip_columns = (-bbx.x).max(0) as usize;
+        let width = bbx.width.saturating_sub(skip_columns);
+        let (source_stride, stride) = (bbx.width.div_ceil(8), width.div_ceil(8));
+        let top = self.ascent - bbx.y - bbx.height as isize;
+        let skip = (-top).max(0) as usize;
+        let y = top.max(0);
+        let height = (bbx.height.saturating_sub(skip)).min(self.height.saturating_sub(y as usize));
+        let mut data = Vec::with_capacity(stride * height);
+        for row in bitmap.iter().skip(skip).take(height) {
+            let row = row.as_ref();
+            if skip_columns == 0 {
+                data.extend((0..stride).map(|i| row.get(i).copied().unwrap_or(0)));
+                continue;
+            }
+            let start = data.len();
+            data.resize(start + stride, 0);
+            for x in 0..width {
+                if bit(row, source_stride, BitOrder::MsbFirst, x + skip_columns, 0) {
+                    data[start + x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+        }
+        data.resize(stride * height, 0);
+        Glyph {
+            data: Cow::Arc(Arc::from(data)),
+            width,
+            height,
+            advance,
+            x: bbx.x.max(0) as usize,
+            y,
+        }
+    }
+}
+
+type RawGlyph = (char, Option<usize>, BoundingBox, Vec<Vec<u8>>);
+
+fn parse_char<'a, I>(lines: &mut I) -> Result<Option<RawGlyph>, FontError>
+where
+    I: Iterator<Item = (usize, &'a [u8])>,
+{
+    let mut encoding = None;
+    let mut advance = None;
+    let mut bbx = None;
+    let mut bitmap = Vec::new();
+    let mut in_bitmap = false;
+    for (n, line) in lines {
+        if in_bitmap && line != b"ENDCHAR" {
+            let row = line
+                .chunks(2)
+                .map(|pair| {
+                    core::str::from_utf8(pair)
+                        .ok()
+                        .and_then(|s| u8::from_str_radix(s, 16).ok())
+                        .ok_or(FontError::InvalidLine(n))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            bitmap.push(row);
+            continue;
+        }
+        let mut words = line.split(|&b| b == b' ').filter(|w| !w.is_empty());
+        match words.next() {
+            Some(b"ENCODING") => {
+                let code: isize = parse_num(n, words.next())?;
+                encoding = u32::try_from(code).ok().and_then(char::from_u32);
+            }
+            Some(b"DWIDTH") => advance = Some(parse_metric(n, words.next())?.max(0) as usize),
+            Some(b"BBX") => bbx = Some(parse_bbx(n, words)?),
+            Some(b"BITMAP") => in_bitmap = true,
+            Some(b"ENDCHAR") => {
+                let bbx = bbx.ok_or(FontError::InvalidLine(n))?;
+                // every row of the bounding box must be there, so that placing the
+                // glyph never allocates more than the file holds
+                let stride = bbx.width.div_ceil(8);
+                if bitmap.len() < bbx.height || bitmap.iter().any(|row| row.len() < stride) {
+                    return Err(FontError::InvalidLine(n));
+                }
+                return Ok(encoding.map(|c| (c, advance, bbx, bitmap)));
+            }
+            _ => {}
+        }
+    }
+    Err(FontError::UnexpectedEof)
+}
+
+fn parse_bbx<'a, I: Iterator<Item = &'a [u8]>>(
+    n: usize,
+    mut words: I,
+) -> Result<BoundingBox, FontError> {
+    let width = parse_metric(n, words.next())?;
+    let height = parse_metric(n, words.next())?;
+    Ok(BoundingBox {
+        width: width.max(0) as usize,
+        height: height.max(0) as usize,
+        x: parse_metric(n, words.next())?,
+        y: parse_metric(n, words.next())?,
+    })
+}
+
+/// Parse a size or offset in pixels, rejecting values no font uses so that
+/// sums of metrics can't overflow
+fn parse_metric(n: usize, word: Option<&[u8]>) -> Result<isize, FontError> {
+    parse_num::<i16>(n, word).map(isize::from)
+}
+
+fn parse_num<T: core::str::FromStr>(n: usize, word: Option<&[u8]>) -> Result<T, FontError> {
+    word.and_then(|w| core::str::from_utf8(w).ok())
+        .and_then(|s| s.parse().ok())
+        .ok_or(FontError::InvalidLine(n))
+}
+
+impl Font for BdfFont {
+    #[inline]
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    fn cell_width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    fn get_glyph(&mut self, c: char) -> Option<Glyph> {
+        self.glyphs.get(&c).cloned()
+    }
+
+    #[inline]
+    fn metrics(&self, c: char) -> Option<Glyph> {
+        self.glyphs.get(&c).map(|glyph| Glyph {
+            data: Cow::none(),
+            ..*glyph
+        })
+    }
+
+    #[inline]
+    fn get_pixel(&self, glyph: &Glyph, x: usize, y: usize) -> Point {
+        let stride = glyph.width.div_ceil(8);
+        Point::Bit(bit(&glyph.data, stride, BitOrder::MsbFirst, x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &[u8] = b"STARTFONT 2.1
+FONT -test-fixed-medium-r-normal--8-80-75-75-c-40-iso10646-1
+SIZE 8 75 75
+FONTBOUNDINGBOX 4 8 0 -2
+STARTPROPERTIES 2
+FONT_ASCENT 6
+FONT_DESCENT 2
+ENDPROPERTIES
+CHARS 2
+STARTCHAR A
+ENCODING 65
+SWIDTH 500 0
+DWIDTH 4 0
+BBX 3 4 0 0
+BITMAP
+40
+A0
+E0
+A0
+ENDCHAR
+STARTCHAR overhang
+ENCODING 66
+DWIDTH 4 0
+BBX 4 1 -2 -2
+BITMAP
+90
+ENDCHAR
+ENDFONT
+";
+
+    fn rows(font: &BdfFont, glyph: &Glyph) -> Vec<String> {
+        (0..glyph.height)
+            .map(|y| {
+                (0..glyph.width)
+                    .map(|x| match font.get_pixel(glyph, x, y) {
+                        Point::Bit(true) => '#',
+                        _ => '.',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse() {
+        let mut font = BdfFont::parse(FONT).unwrap();
+        assert_eq!((font.height(), font.cell_width()), (8, 4));
+        assert_eq!(font.chars().collect::<Vec<_>>(), ['A', 'B']);
+        let glyph = font.get_glyph('A').unwrap();
+        assert_eq!((glyph.x, glyph.y, glyph.advance), (0, 2, 4));
+        assert_eq!(rows(&font, &glyph), [".#.", "#.#", "###", "#.#"]);
+        assert!(font.get_glyph('C').is_none());
+    }
+
+    #[test]
+    fn negative_offset_is_cropped() {
+        let mut font = BdfFont::parse(FONT).unwrap();
+        let glyph = font.get_glyph('B').unwrap();
+        assert_eq!((glyph.x, glyph.y, glyph.width), (0, 7, 2));
+        assert_eq!(rows(&font, &glyph), [".#"]);
+    }
+
+    #[test]
+    fn cell_width_from_digit_advance() {
+        let wide = core::str::from_utf8(FONT)
+            .unwrap()
+            .replace("FONTBOUNDINGBOX 4", "FONTBOUNDINGBOX 16");
+        let font = BdfFont::parse(wide.as_bytes()).unwrap();
+        assert_eq!(font.cell_width(), 16);
+        let font = BdfFont::parse(wide.replace("ENCODING 65", "ENCODING 48").as_bytes()).unwrap();
+        assert_eq!(font.cell_width(), 4);
+    }
+
+    #[test]
+    fn reject_out_of_range_metrics() {
+        let parse = |from: &str, to: &str| {
+            let data = core::str::from_utf8(FONT).unwrap().replace(from, to);
+            BdfFont::parse(data.as_bytes()).err()
+        };
+        let ascent = parse("FONT_ASCENT 6", "FONT_ASCENT 9223372036854775807");
+        assert_eq!(ascent, Some(FontError::InvalidLine(6)));
+        let bbx = parse("BBX 3 4 0 0", "BBX 3 99999999999 0 0");
+        assert_eq!(bbx, Some(FontError::InvalidLine(14)));
+        // a bounding box larger than the bitmap given
+        let bbx = parse("BBX 3 4 0 0", "BBX 30000 30000 0 0");
+        assert_eq!(bbx, Some(FontError::InvalidLine(20)));
+    }
+}
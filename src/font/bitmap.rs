@@ -0,0 +1,173 @@
+use super::{Cow, Font, Glyph, Point};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BitOrder {
+    /// The leftmost pixel is the most significant bit (PSF, BDF, VGA)
+    MsbFirst,
+    /// The leftmost pixel is the least significant bit
+    LsbFirst,
+}
+
+/// Fixed size bitmap font over glyph data supplied by the user.
+///
+/// Glyph `i` is stored at `data[i * stride * height..]`, one row of `stride` bytes
+/// after another. Codepoints map to glyph indices either directly (`first + i`)
+/// or through a sparse table sorted by codepoint. It is `Copy`, so a `static`
+/// font can be handed to `Fbterm::new` directly.
+#[derive(Copy, Clone)]
+pub struct BitmapFont {
+    data: &'static [u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    bit_order: BitOrder,
+    map: CodepointMap,
+}
+
+#[derive(Copy, Clone)]
+enum CodepointMap {
+    Direct(u32),
+    Sparse(&'static [(char, u32)]),
+}
+
+impl BitmapFont {
+    /// Glyphs of `width` x `height` pixels with rows padded to whole bytes,
+    /// glyph 0 being U+0000
+    pub const fn new(data: &'static [u8], width: usize, height: usize) -> BitmapFont {
+        BitmapFont {
+            data,
+            width,
+            height,
+            stride: width.div_ceil(8),
+            bit_order: BitOrder::MsbFirst,
+            map: CodepointMap::Direct(0),
+        }
+    }
+
+    /// Number of bytes per glyph row
+    pub const fn with_stride(mut self, stride: usize) -> BitmapFont {
+        self.stride = stride;
+        self
+    }
+
+    pub const fn with_bit_order(mut self, bit_order: BitOrder) -> BitmapFont {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Glyph 0 is `first`, glyph 1 is `first + 1`, ...
+    pub const fn with_first(mut self, first: char) -> BitmapFont {
+        self.map = CodepointMap::Direct(first as u32);
+        self
+    }
+
+    /// `table` maps codepoints to glyph indices and must be sorted by codepoint
+    pub const fn with_table(mut self, table: &'static [(char, u32)]) -> BitmapFont {
+        self.map = CodepointMap::Sparse(table);
+        self
+    }
+
+    #[inline]
+    fn glyph_size(&self) -> usize {
+        self.stride * self.height
+    }
+
+    fn index(&self, c: char) -> Option<usize> {
+        let index = match self.map {
+            CodepointMap::Direct(first) => (c as u32).checked_sub(first)? as usize,
+            CodepointMap::Sparse(table) => {
+                let i = table.binary_search_by_key(&c, |&(c, _)| c).ok()?;
+                table[i].1 as usize
+            }
+        };
+        if self.glyph_size() == 0 || index >= self.data.len() / self.glyph_size() {
+            return None;
+        }
+        Some(index)
+    }
+}
+
+impl Font for BitmapFont {
+    #[inline]
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    fn cell_width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    fn get_glyph(&mut self, c: char) -> Option<Glyph> {
+        let index = self.index(c)?;
+        let size = self.glyph_size();
+        Some(Glyph {
+            data: self.data[index * size..(index + 1) * size].into(),
+            ..self.metrics(c)?
+        })
+    }
+
+    #[inline]
+    fn metrics(&self, c: char) -> Option<Glyph> {
+        self.index(c)?;
+        Some(Glyph {
+            data: Cow::none(),
+            width: self.width,
+            advance: self.width,
+            height: self.height,
+            x: 0,
+            y: 0,
+        })
+    }
+
+    #[inline]
+    fn get_pixel(&self, glyph: &Glyph, x: usize, y: usize) -> Point {
+        Point::Bit(bit(&glyph.data, self.stride, self.bit_order, x, y))
+    }
+}
+
+/// Read pixel (`x`, `y`) of a packed 1 bit per pixel bitmap
+#[inline]
+pub(crate) fn bit(data: &[u8], stride: usize, order: BitOrder, x: usize, y: usize) -> bool {
+    let byte = match data.get(y * stride + x / 8) {
+        Some(&byte) => byte,
+        None => return false,
+    };
+    let shift = match order {
+        BitOrder::MsbFirst => 7 - x % 8,
+        BitOrder::LsbFirst => x % 8,
+    };
+    (byte >> shift) & 0x1 == 0x1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fb::gray_fb;
+    use crate::Fbterm;
+
+    /// `!` as a 4x4 glyph, as `include_font!` would generate it
+    static FONT: BitmapFont =
+        BitmapFont::new(&[0x40, 0x40, 0x00, 0x40], 4, 4).with_table(&[('!', 0)]);
+
+    #[test]
+    fn static_font_draws() {
+        let mut buffer = [0u8; 8 * 4];
+        let fb = gray_fb(&mut buffer, 8, 4);
+        let mut term = Fbterm::new(fb, FONT);
+        term.clear();
+        term.print("!");
+        let column = term
+            .framebuffer
+            .front()
+            .chunks(8)
+            .map(|row| row[1])
+            .collect::<Vec<_>>();
+        assert_eq!(column, [255, 255, 0, 255]);
+        assert_eq!(
+            term.framebuffer.front().iter().filter(|&&p| p != 0).count(),
+            3
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use super::{Cow, Font, Glyph, Point};
+use alloc::{boxed::Box, collections::BTreeMap, sync::Arc, vec, vec::Vec};
+
+/// Chain of fonts, each character being drawn by the first font that has a glyph for it.
+///
+/// Glyphs of the fallback fonts are centered vertically in the line of the first
+/// (primary) font and clipped to it. Glyphs keep the kind of pixels their font
+/// draws: bits, grayscale or subpixel coverage.
+pub struct FallbackFont {
+    fonts: Vec<Box<dyn Font>>,
+    covered: BTreeMap<char, Option<usize>>,
+    cache: lru::LruCache<char, Glyph>,
+}
+
+impl FallbackFont {
+    pub fn new<F: Font + 'static>(primary: F) -> FallbackFont {
+        FallbackFont {
+            fonts: vec![Box::new(primary)],
+            covered: BTreeMap::new(),
+            cache: lru::LruCache::new(128),
+        }
+    }
+
+    /// Append a font tried after all the fonts already in the chain
+    pub fn push<F: Font + 'static>(&mut self, font: F) {
+        self.fonts.push(Box::new(font));
+        self.covered.retain(|_, index| index.is_some());
+    }
+
+    /// Index in the chain of the font drawing `c`
+    pub fn font_for(&mut self, c: char) -> Option<usize> {
+        if let Some(&index) = self.covered.get(&c) {
+            return index;
+        }
+        let index = self.fonts.iter().position(|font| font.metrics(c).is_some());
+        self.covered.insert(c, index);
+        index
+    }
+
+    /// Move `glyph` of font `index` into the primary font's cell, or two cells
+    /// for a wide glyph, returning it with the number of rows cut from its top
+    fn normalize(&self, index: usize, glyph: Glyph) -> (Glyph, usize) {
+        let primary = &self.fonts[0];
+        let font = &self.fonts[index];
+        let height = primary.height() as isize;
+        let shift = (height - font.height() as isize) / 2;
+        let top = glyph.y + shift;
+        let skip = (-top).max(0) as usize;
+        let y = top.max(0);
+        let rows = glyph
+            .height
+            .saturating_sub(skip)
+            .min((height - y).max(0) as usize);
+        let cells = if glyph.advance * 2 > font.cell_width() * 3 {
+            2
+        } else {
+            1
+        };
+        let advance = primary.cell_width() * cells;
+        let x = glyph.x.min(advance);
+        (
+            Glyph {
+                width: glyph.width.min(advance - x),
+                height: rows,
+                advance,
+                x,
+                y,
+                ..glyph
+            },
+            skip,
+        )
+    }
+}
+
+impl Font for FallbackFont {
+    #[inline]
+    fn height(&self) -> usize {
+        self.fonts[0].height()
+    }
+
+    #[inline]
+    fn cell_width(&self) -> usize {
+        self.fonts[0].cell_width()
+    }
+
+    fn get_glyph(&mut self, c: char) -> Option<Glyph> {
+        if let Some(glyph) = self.cache.get(&c) {
+            return Some(glyph.clone());
+        }
+        let index = self.font_for(c)?;
+        let font = &mut self.fonts[index];
+        let source = font.get_glyph(c)?;
+        let (glyph, skip) = self.normalize(index, source.clone());
+        let font = &self.fonts[index];
+        let points = (0..glyph.height)
+            .flat_map(|y| (0..glyph.width).map(move |x| (x, y)))
+            .map(|(x, y)| font.get_pixel(&source, x, y + skip))
+            .collect::<Vec<_>>();
+        let glyph = Glyph {
+            data: Cow::Arc(Arc::from(encode(&points))),
+            ..glyph
+        };
+        self.cache.put(c, glyph.clone());
+        Some(glyph)
+    }
+
+    fn metrics(&self, c: char) -> Option<Glyph> {
+        let index = match self.covered.get(&c) {
+            Some(&index) => index?,
+            None => self
+                .fonts
+                .iter()
+                .position(|font| font.metrics(c).is_some())?,
+        };
+        let glyph = self.fonts[index].metrics(c)?;
+        Some(self.normalize(index, glyph).0)
+    }
+
+    #[inline]
+    fn get_pixel(&self, glyph: &Glyph, x: usize, y: usize) -> Point {
+        let kind = glyph.data.first().copied().unwrap_or(COVERAGE);
+        let start = 1 + (y * glyph.width + x) * kind.max(1) as usize;
+        let byte = |i| glyph.data.get(start + i).copied().unwrap_or(0);
+        match kind {
+            BIT => Point::Bit(byte(0) != 0),
+            SUBPIXEL => Point::SubpixelCoverage(byte(0), byte(1), byte(2)),
+            _ => Point::Coverage(byte(0)),
+        }
+    }
+}
+
+// Kind of the pixels of a cached glyph, stored in its first byte: each pixel
+// then takes one byte, or three bytes of subpixel coverage
+const BIT: u8 = 0;
+const COVERAGE: u8 = 1;
+const SUBPIXEL: u8 = 3;
+
+/// Cached glyph data of `points`, in the widest kind any of them needs
+fn encode(points: &[Point]) -> Vec<u8> {
+    let kind = points
+        .iter()
+        .map(|point| match point {
+            Point::Bit(_) => BIT,
+            Point::Coverage(_) => COVERAGE,
+            Point::SubpixelCoverage(..) => SUBPIXEL,
+        })
+        .max()
+        .unwrap_or(BIT);
+    let mut data = Vec::with_capacity(1 + points.len() * kind.max(1) as usize);
+    data.push(kind);
+    for point in points {
+        let (r, g, b) = match *point {
+            Point::Bit(bit) if kind == BIT => (bit as u8, 0, 0),
+            Point::Bit(bit) => (bit as u8 * 255, bit as u8 * 255, bit as u8 * 255),
+            Point::Coverage(cov) => (cov, cov, cov),
+            Point::SubpixelCoverage(r, g, b) => (r, g, b),
+        };
+        match kind {
+            SUBPIXEL => data.extend([r, g, b]),
+            _ => data.push(r),
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BitmapFont;
+
+    /// Font drawing every pixel of its only glyph with red subpixels
+    struct Red;
+
+    impl Font for Red {
+        fn height(&self) -> usize {
+            4
+        }
+        fn cell_width(&self) -> usize {
+            4
+        }
+        fn get_glyph(&mut self, c: char) -> Option<Glyph> {
+            self.metrics(c)
+        }
+        fn metrics(&self, c: char) -> Option<Glyph> {
+            (c == 'r').then(|| Glyph {
+                width: 4,
+                height: 4,
+                advance: 4,
+                x: 0,
+                y: 0,
+                data: Cow::none(),
+            })
+        }
+        fn get_pixel(&self, _glyph: &Glyph, _x: usize, _y: usize) -> Point {
+            Point::SubpixelCoverage(255, 0, 0)
+        }
+    }
+
+    #[test]
+    fn glyphs_keep_their_kind_of_pixels() {
+        let primary = BitmapFont::new(&[0x80; 4], 4, 4).with_table(&[('#', 0)]);
+        let mut font = FallbackFont::new(primary);
+        font.push(Red);
+        let glyph = font.get_glyph('#').unwrap();
+        assert!(matches!(font.get_pixel(&glyph, 0, 0), Point::Bit(true)));
+        assert!(matches!(font.get_pixel(&glyph, 1, 0), Point::Bit(false)));
+        let glyph = font.get_glyph('r').unwrap();
+        assert!(matches!(
+            font.get_pixel(&glyph, 3, 3),
+            Point::SubpixelCoverage(255, 0, 0)
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use super::{
+    truetype::{TrueTypeFont, TrueTypeSettings},
+    Font, FontError, Glyph, Point, Style,
+};
+
+/// Regular, bold, italic and bold italic faces of a TrueType font, all laid out
+/// on the cells of the regular face.
+///
+/// A style without its own face is drawn with the closest one available,
+/// down to the regular face.
+pub struct FontFamily {
+    regular: TrueTypeFont,
+    bold: Option<TrueTypeFont>,
+    italic: Option<TrueTypeFont>,
+    bold_italic: Option<TrueTypeFont>,
+}
+
+impl FontFamily {
+    pub fn new(regular: TrueTypeFont) -> FontFamily {
+        FontFamily {
+            regular,
+            bold: None,
+            italic: None,
+            bold_italic: None,
+        }
+    }
+
+    /// Draw `style` with `face`, replacing the face it had. `Style::Regular` is ignored.
+    pub fn with_face(mut self, style: Style, mut face: TrueTypeFont) -> FontFamily {
+        if style == Style::Regular {
+            return self;
+        }
+        face.fit(&self.regular);
+        match style {
+            Style::Regular => {}
+            Style::Bold => self.bold = Some(face),
+            Style::Italic => self.italic = Some(face),
+            Style::BoldItalic => self.bold_italic = Some(face),
+        }
+        self
+    }
+
+    #[inline]
+    pub fn regular(&self) -> &TrueTypeFont {
+        &self.regular
+    }
+
+    /// Change the pixel size of every face
+    pub fn set_size(&mut self, size: f32) -> Result<(), FontError> {
+        self.regular.set_size(size)?;
+        for face in self.styled_faces() {
+            face.set_size(size)?;
+        }
+        self.refit();
+        Ok(())
+    }
+
+    /// Change the settings of the regular face, the other faces following its metrics
+    pub fn set_settings(&mut self, settings: TrueTypeSettings) {
+        self.regular.set_settings(settings);
+        self.refit();
+    }
+
+    fn styled_faces(&mut self) -> impl Iterator<Item = &mut TrueTypeFont> {
+        self.bold
+            .iter_mut()
+            .chain(self.italic.iter_mut())
+            .chain(self.bold_italic.iter_mut())
+    }
+
+    fn refit(&mut self) {
+        let FontFamily {
+            regular,
+            bold,
+            italic,
+            bold_italic,
+        } = self;
+        for face in bold.iter_mut().chain(italic).chain(bold_italic) {
+            face.fit(regular);
+        }
+    }
+
+    fn face(&mut self, style: Style) -> &mut TrueTypeFont {
+        let face = match style {
+            Style::Regular => None,
+            Style::Bold => self.bold.as_mut(),
+            Style::Italic => self.italic.as_mut(),
+            Style::BoldItalic => self
+                .bold_italic
+                .as_mut()
+                .or(self.bold.as_mut())
+                .or(self.italic.as_mut()),
+        };
+        face.unwrap_or(&mut self.regular)
+    }
+}
+
+impl Font for FontFamily {
+    #[inline]
+    fn height(&self) -> usize {
+        self.regular.height()
+    }
+
+    #[inline]
+    fn cell_width(&self) -> usize {
+        self.regular.cell_width()
+    }
+
+    #[inline]
+    fn get_glyph(&mut self, c: char) -> Option<Glyph> {
+        self.regular.get_glyph(c)
+    }
+
+    fn get_styled_glyph(&mut self, c: char, style: Style) -> Option<Glyph> {
+        self.face(style)
+            .get_glyph(c)
+            .or_else(|| self.regular.get_glyph(c))
+    }
+
+    #[inline]
+    fn metrics(&self, c: char) -> Option<Glyph> {
+        self.regular.metrics(c)
+    }
+
+    #[inline]
+    fn get_pixel(&self, glyph: &Glyph, x: usize, y: usize) -> Point {
+        self.regular.get_pixel(glyph, x, y)
+    }
+
+    #[inline]
+    fn notdef(&mut self) -> Option<Glyph> {
+        self.regular.notdef()
+    }
+}
@@ -0,0 +1,115 @@
+/// What `Fbterm` draws for a character no glyph exists for
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum MissingGlyph {
+    /// A box containing the codepoint in hex digits, like unifont
+    #[default]
+    HexBox,
+    /// The font's `.notdef` glyph, or a hex box if it has none
+    Notdef,
+    /// A blank space
+    Space,
+}
+
+/// 3x5 pixel hex digits, 3 bits per row
+const DIGITS: [[u8; 5]; 16] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b111, 0b101, 0b111, 0b101, 0b101],
+    [0b110, 0b101, 0b110, 0b101, 0b110],
+    [0b111, 0b100, 0b100, 0b100, 0b111],
+    [0b110, 0b101, 0b101, 0b101, 0b110],
+    [0b111, 0b100, 0b111, 0b100, 0b111],
+    [0b111, 0b100, 0b111, 0b100, 0b100],
+];
+
+/// Procedural placeholder: a box with the codepoint written on two rows of
+/// hex digits, one cell wide with 2 digits per row up to U+FFFF, and two cells
+/// wide with 3 digits per row above, so the text stays on the cell grid.
+/// Digits are dropped when the cell is too small to hold them.
+pub(crate) struct HexBox {
+    code: u32,
+    columns: usize,
+    width: usize,
+    height: usize,
+    scale: usize,
+    left: usize,
+    top: usize,
+}
+
+impl HexBox {
+    pub(crate) fn new(c: char, cell_width: usize, height: usize) -> HexBox {
+        let code = c as u32;
+        let columns = if code > 0xffff { 3 } else { 2 };
+        let width = if columns == 3 {
+            cell_width * 2
+        } else {
+            cell_width
+        };
+        // digits are 3s x 5s with a gap of s, inside a border and a margin of 1 pixel
+        let content_width = 4 * columns - 1;
+        let scale = (width.saturating_sub(6) / content_width).min(height.saturating_sub(6) / 11);
+        HexBox {
+            code,
+            columns,
+            width,
+            height,
+            scale,
+            left: (width - content_width * scale) / 2,
+            top: (height - 11 * scale) / 2,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub(crate) fn height(&self) -> usize {
+        self.height
+    }
+
+    pub(crate) fn pixel(&self, x: usize, y: usize) -> bool {
+        if self.width < 4 || self.height < 4 {
+            return false;
+        }
+        let (right, bottom) = (self.width - 2, self.height - 2);
+        if x < 1 || y < 1 || x > right || y > bottom {
+            return false;
+        }
+        if x == 1 || y == 1 || x == right || y == bottom {
+            return true;
+        }
+        if self.scale == 0 || x < self.left || y < self.top {
+            return false;
+        }
+        let (dx, dy) = ((x - self.left) / self.scale, (y - self.top) / self.scale);
+        let (column, row) = (dx / 4, dy / 6);
+        if column >= self.columns || row >= 2 || dx % 4 == 3 || dy % 6 == 5 {
+            return false;
+        }
+        let shift = 4 * (2 * self.columns - 1 - (row * self.columns + column));
+        let digit = (self.code >> shift) & 0xf;
+        (DIGITS[digit as usize][dy % 6] >> (2 - dx % 4)) & 0x1 == 0x1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_are_whole_cells() {
+        assert_eq!(HexBox::new('\u{0378}', 8, 16).width(), 8);
+        assert_eq!(HexBox::new('\u{1F600}', 8, 16).width(), 16);
+        assert_eq!(HexBox::new('\u{1F600}', 7, 16).width(), 14);
+    }
+}
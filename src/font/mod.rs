@@ -1,22 +1,73 @@
 #[cfg(feature = "alloc")]
+pub(crate) mod bdf;
+#[cfg(feature = "alloc")]
+pub(crate) mod fallback;
+#[cfg(feature = "alloc")]
+pub(crate) mod family;
+#[cfg(feature = "alloc")]
+pub(crate) mod subpixel;
+#[cfg(feature = "alloc")]
 pub(crate) mod truetype;
 
+pub(crate) mod bitmap;
+pub(crate) mod missing;
+pub(crate) mod procedural;
 pub(crate) mod vga;
 
 #[cfg(feature = "alloc")]
 use alloc::sync::Arc;
 use core::ops::Deref;
 
+/// Error loading a font
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FontError {
+    /// The TrueType parser rejected the font data
+    Parse(&'static str),
+    /// The font has no line metrics to lay out lines with
+    MissingLineMetrics,
+    /// The pixel size is not a positive number
+    InvalidSize,
+    /// Line number (starting at 1) of a BDF file that could not be parsed
+    InvalidLine(usize),
+    /// The BDF file has no `FONTBOUNDINGBOX` line
+    MissingBoundingBox,
+    /// The BDF file ends in the middle of a character
+    UnexpectedEof,
+}
+
+/// Face a character is drawn with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Style {
+    #[default]
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
 pub enum Point {
     Bit(bool),
     Coverage(u8),
+    /// Coverage of the red, green and blue subpixels
+    SubpixelCoverage(u8, u8, u8),
 }
 
 pub trait Font {
     fn height(&self) -> usize;
+    /// Advance of a single width character, wide characters taking two cells
+    fn cell_width(&self) -> usize;
     fn get_glyph(&mut self, c: char) -> Option<Glyph>;
+    /// Glyph of `c` in `style`, fonts with a single face ignoring it
+    fn get_styled_glyph(&mut self, c: char, style: Style) -> Option<Glyph> {
+        let _ = style;
+        self.get_glyph(c)
+    }
     fn metrics(&self, c: char) -> Option<Glyph>;
     fn get_pixel(&self, glyph: &Glyph, x: usize, y: usize) -> Point;
+    /// Glyph drawn for characters missing from the font
+    fn notdef(&mut self) -> Option<Glyph> {
+        self.get_glyph('\u{FFFD}')
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -0,0 +1,237 @@
+use super::Point;
+
+const NONE: u8 = 0;
+const LIGHT: u8 = 1;
+const HEAVY: u8 = 2;
+const DOUBLE: u8 = 3;
+
+/// Line weights of U+2500..=U+257F as `up | right << 2 | down << 4 | left << 6`,
+/// arcs and diagonals are 0
+const LINES: [u8; 128] = [
+    0x44, 0x88, 0x11, 0x22, 0x44, 0x88, 0x11, 0x22, 0x44, 0x88, 0x11, 0x22, 0x14, 0x18, 0x24, 0x28,
+    0x50, 0x90, 0x60, 0xa0, 0x05, 0x09, 0x06, 0x0a, 0x41, 0x81, 0x42, 0x82, 0x15, 0x19, 0x16, 0x25,
+    0x26, 0x1a, 0x29, 0x2a, 0x51, 0x91, 0x52, 0x61, 0x62, 0x92, 0xa1, 0xa2, 0x54, 0x94, 0x58, 0x98,
+    0x64, 0xa4, 0x68, 0xa8, 0x45, 0x85, 0x49, 0x89, 0x46, 0x86, 0x4a, 0x8a, 0x55, 0x95, 0x59, 0x99,
+    0x56, 0x65, 0x66, 0x96, 0x5a, 0xa5, 0x69, 0x9a, 0xa9, 0xa6, 0x6a, 0xaa, 0x44, 0x88, 0x11, 0x22,
+    0xcc, 0x33, 0x1c, 0x34, 0x3c, 0xd0, 0x70, 0xf0, 0x0d, 0x07, 0x0f, 0xc1, 0x43, 0xc3, 0x1d, 0x37,
+    0x3f, 0xd1, 0x73, 0xf3, 0xdc, 0x74, 0xfc, 0xcd, 0x47, 0xcf, 0xdd, 0x77, 0xff, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x40, 0x01, 0x04, 0x10, 0x80, 0x02, 0x08, 0x20, 0x48, 0x21, 0x84, 0x12,
+];
+
+/// Whether `c` is drawn by `CellGlyph`: box drawing, block elements, braille
+/// patterns and the Powerline separators
+#[inline]
+pub(crate) fn covers(c: char) -> bool {
+    matches!(c as u32, 0x2500..=0x259f | 0x2800..=0x28ff | 0xe0b0..=0xe0b3)
+}
+
+/// Pixel exact glyph filling a whole cell, so lines join with the neighbouring cells
+pub(crate) struct CellGlyph {
+    code: u32,
+    width: i32,
+    height: i32,
+    light: i32,
+}
+
+impl CellGlyph {
+    pub(crate) fn new(c: char, width: usize, height: usize) -> CellGlyph {
+        CellGlyph {
+            code: c as u32,
+            width: width as i32,
+            height: height as i32,
+            light: (width as i32 / 10).max(1),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn width(&self) -> usize {
+        self.width as usize
+    }
+
+    #[inline]
+    pub(crate) fn height(&self) -> usize {
+        self.height as usize
+    }
+
+    pub(crate) fn point(&self, x: usize, y: usize) -> Point {
+        let (x, y) = (x as i32, y as i32);
+        match self.code {
+            0x256d..=0x2570 => Point::Bit(self.arc(x, y)),
+            0x2571..=0x2573 => Point::Bit(self.diagonal(x, y)),
+            0x2500..=0x257f => Point::Bit(self.lines(x, y)),
+            0x2591..=0x2593 => Point::Coverage(((self.code - 0x2590) * 64).min(255) as u8),
+            0x2580..=0x259f => Point::Bit(self.block(x, y)),
+            0x2800..=0x28ff => Point::Bit(self.braille(x, y)),
+            _ => Point::Bit(self.powerline(x, y)),
+        }
+    }
+
+    fn thickness(&self, weight: u8) -> i32 {
+        match weight {
+            NONE => 0,
+            LIGHT => self.light,
+            HEAVY => self.light * 2,
+            _ => self.light * 3,
+        }
+    }
+
+    fn lines(&self, x: i32, y: i32) -> bool {
+        let arms = LINES[(self.code - 0x2500) as usize];
+        let weight = |shift: u8| (arms >> shift) & 0x3;
+        let (up, right, down, left) = (weight(0), weight(2), weight(4), weight(6));
+        let dashes = match self.code {
+            0x2504..=0x2507 => 3,
+            0x2508..=0x250b => 4,
+            0x254c..=0x254f => 2,
+            _ => 0,
+        };
+        if dashes > 0 {
+            let (pos, len) = if left != NONE {
+                (x, self.width)
+            } else {
+                (y, self.height)
+            };
+            if (pos * dashes) % len >= len * 2 / 3 {
+                return false;
+            }
+        }
+        let (cx, cy) = (self.width / 2, self.height / 2);
+        self.arm(right, x - cx, y - cy, up, down)
+            || self.arm(left, cx - x, y - cy, up, down)
+            || self.arm(down, y - cy, x - cx, left, right)
+            || self.arm(up, cy - y, x - cx, left, right)
+    }
+
+    /// Whether a pixel at `along` pixels from the center in the direction of an
+    /// arm and `across` pixels from its axis is covered by it. `neg` and `pos`
+    /// are the weights of the perpendicular arms on either side.
+    fn arm(&self, weight: u8, along: i32, across: i32, neg: u8, pos: u8) -> bool {
+        let gap = self.light;
+        let joint = -(self.thickness(neg).max(self.thickness(pos)) / 2);
+        match weight {
+            NONE => false,
+            DOUBLE => {
+                let (offset, same, other) = if across < 0 {
+                    (-gap, neg, pos)
+                } else {
+                    (gap, pos, neg)
+                };
+                let start = if same == DOUBLE {
+                    gap
+                } else if other == DOUBLE {
+                    -gap
+                } else {
+                    joint
+                };
+                band(across - offset, self.light) && along >= start
+            }
+            _ => {
+                let start = if neg == DOUBLE && pos == DOUBLE {
+                    gap
+                } else if neg == DOUBLE || pos == DOUBLE {
+                    -gap
+                } else {
+                    joint.min(-(self.thickness(weight) / 2))
+                };
+                band(across, self.thickness(weight)) && along >= start
+            }
+        }
+    }
+
+    /// Quarter circle joining two arms, drawn as if it was U+256D and mirrored
+    fn arc(&self, x: i32, y: i32) -> bool {
+        let (w, h) = (self.width, self.height);
+        let (x, cx) = match self.code {
+            0x256d | 0x2570 => (x, w / 2),
+            _ => (w - 1 - x, w - 1 - w / 2),
+        };
+        let (y, cy) = match self.code {
+            0x256d | 0x256e => (y, h / 2),
+            _ => (h - 1 - y, h - 1 - h / 2),
+        };
+        let radius = (w - 1 - cx).min(h - 1 - cy).max(0);
+        if x > cx + radius {
+            return band(y - cy, self.light);
+        }
+        if y > cy + radius {
+            return band(x - cx, self.light);
+        }
+        let (dx, dy) = (2 * (x - cx - radius), 2 * (y - cy - radius));
+        let distance = dx * dx + dy * dy;
+        let (inner, outer) = (2 * radius - self.light, 2 * radius + self.light);
+        distance >= inner * inner && distance <= outer * outer
+    }
+
+    fn diagonal(&self, x: i32, y: i32) -> bool {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (px, py) = (2 * x as i64 + 1, 2 * y as i64 + 1);
+        let limit = (self.light as i64).pow(2) * (w * w + h * h);
+        let rising = (h * px + w * py - 2 * w * h).pow(2) <= limit;
+        let falling = (h * px - w * py).pow(2) <= limit;
+        match self.code {
+            0x2571 => rising,
+            0x2572 => falling,
+            _ => rising || falling,
+        }
+    }
+
+    fn block(&self, x: i32, y: i32) -> bool {
+        let (w, h) = (self.width, self.height);
+        match self.code {
+            0x2580 => y * 2 < h,
+            0x2581..=0x2588 => y * 8 >= h * (8 - (self.code - 0x2580) as i32),
+            0x2589..=0x258f => x * 8 < w * (8 - (self.code - 0x2588) as i32),
+            0x2590 => x * 2 >= w,
+            0x2594 => y * 8 < h,
+            0x2595 => x * 8 >= w * 7,
+            _ => {
+                // upper left, upper right, lower left and lower right quadrants as bits 0 to 3
+                const QUADRANTS: [u8; 10] = [4, 8, 1, 13, 9, 7, 11, 2, 6, 14];
+                let quadrant = ((y * 2 >= h) as u8) << 1 | (x * 2 >= w) as u8;
+                (QUADRANTS[(self.code - 0x2596) as usize] >> quadrant) & 0x1 == 0x1
+            }
+        }
+    }
+
+    fn braille(&self, x: i32, y: i32) -> bool {
+        let (w, h) = (self.width, self.height);
+        let (column, row) = (x * 2 / w, y * 4 / h);
+        let bit = match (column, row) {
+            (0, 3) => 6,
+            (1, 3) => 7,
+            (column, row) => column * 3 + row,
+        };
+        if (self.code >> bit) & 0x1 == 0 {
+            return false;
+        }
+        let (left, right) = (column * w / 2, (column + 1) * w / 2);
+        let (top, bottom) = (row * h / 4, (row + 1) * h / 4);
+        let size = ((right - left).min(bottom - top) / 2).max(1);
+        let (dx, dy) = (
+            x - left - (right - left - size) / 2,
+            y - top - (bottom - top - size) / 2,
+        );
+        dx >= 0 && dx < size && dy >= 0 && dy < size
+    }
+
+    /// Triangle separators pointing right, mirrored for U+E0B2 and U+E0B3
+    fn powerline(&self, x: i32, y: i32) -> bool {
+        let (w, h) = (self.width, self.height);
+        let x = match self.code {
+            0xe0b0 | 0xe0b1 => x,
+            _ => w - 1 - x,
+        };
+        let edge = 2 * w * (h - (2 * y + 1 - h).abs());
+        let px = (2 * x + 1) * h;
+        match self.code {
+            0xe0b0 | 0xe0b2 => px <= edge,
+            _ => (px - edge).abs() <= h * self.light,
+        }
+    }
+}
+
+/// Whether `offset` falls in a line `thickness` pixels wide centered on 0
+#[inline]
+fn band(offset: i32, thickness: i32) -> bool {
+    offset >= -(thickness / 2) && offset < thickness - thickness / 2
+}
@@ -0,0 +1,131 @@
+use alloc::{vec, vec::Vec};
+
+/// Order of the color stripes of an LCD pixel
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SubpixelOrder {
+    /// Left to right
+    Rgb,
+    Bgr,
+    /// Top to bottom
+    VRgb,
+    VBgr,
+}
+
+impl SubpixelOrder {
+    #[inline]
+    fn horizontal(self) -> bool {
+        matches!(self, SubpixelOrder::Rgb | SubpixelOrder::Bgr)
+    }
+}
+
+/// Weights of the filter spreading each subpixel over its neighbours, to soften color fringes
+const FILTER: [u32; 5] = [1, 2, 3, 2, 1];
+
+/// Metrics of a glyph rendered by `rasterize` from its metrics at three times the size
+pub(crate) fn metrics(
+    large: &fontdue::Metrics,
+    order: Option<SubpixelOrder>,
+    snap: bool,
+) -> fontdue::Metrics {
+    let scaled = fontdue::Metrics {
+        advance_width: large.advance_width / 3.0,
+        advance_height: large.advance_height / 3.0,
+        bounds: large.bounds.scale(1.0 / 3.0),
+        ..fontdue::Metrics::default()
+    };
+    if large.width == 0 || large.height == 0 {
+        return scaled;
+    }
+    // the filter reaches 2 subpixels past the glyph on the subpixel axis
+    let (margin_x, margin_y) = match order {
+        Some(order) if order.horizontal() => (2, 0),
+        Some(_) => (0, 2),
+        None => (0, 0),
+    };
+    let left = large.xmin + shift(large, snap);
+    let xmin = (left - margin_x).div_euclid(3);
+    let xmax = ceil3(left + large.width as i32 + margin_x);
+    let ymin = (large.ymin - margin_y).div_euclid(3);
+    let ymax = ceil3(large.ymin + large.height as i32 + margin_y);
+    fontdue::Metrics {
+        xmin,
+        ymin,
+        width: (xmax - xmin) as usize,
+        height: (ymax - ymin) as usize,
+        ..scaled
+    }
+}
+
+/// Rasterize glyph `index` at three times `size` and fold every 3x3 block of
+/// samples into one pixel, with a coverage per channel (3 bytes per pixel) if
+/// `order` is set. `snap` moves the left edge of the glyph on a pixel boundary.
+pub(crate) fn rasterize(
+    font: &fontdue::Font,
+    index: usize,
+    size: f32,
+    order: Option<SubpixelOrder>,
+    snap: bool,
+) -> (fontdue::Metrics, Vec<u8>) {
+    let (large, samples) = font.rasterize_indexed(index, size * 3.0);
+    let metrics = metrics(&large, order, snap);
+    let left = large.xmin + shift(&large, snap);
+    let sample = |x: i32, y: i32| {
+        let column = x - left;
+        let row = large.ymin + large.height as i32 - 1 - y;
+        if column < 0 || row < 0 || column >= large.width as i32 || row >= large.height as i32 {
+            return 0;
+        }
+        samples[row as usize * large.width + column as usize] as u32
+    };
+    let channels = if order.is_some() { 3 } else { 1 };
+    let mut data = vec![0; metrics.width * metrics.height * channels];
+    let top = metrics.ymin + metrics.height as i32 - 1;
+    for (i, pixel) in data.chunks_mut(channels).enumerate() {
+        let x = metrics.xmin + (i % metrics.width) as i32;
+        let y = top - (i / metrics.width) as i32;
+        let order = match order {
+            Some(order) => order,
+            None => {
+                let sum = (0..9)
+                    .map(|j| sample(3 * x + j % 3, 3 * y + j / 3))
+                    .sum::<u32>();
+                pixel[0] = (sum / 9) as u8;
+                continue;
+            }
+        };
+        for (k, channel) in pixel.iter_mut().enumerate() {
+            let k = k as i32;
+            let mut sum = 0;
+            for (offset, weight) in (-2..=2).zip(FILTER.iter()) {
+                for j in 0..3 {
+                    sum += weight
+                        * if order.horizontal() {
+                            sample(3 * x + k + offset, 3 * y + j)
+                        } else {
+                            sample(3 * x + j, 3 * y + 2 - k + offset)
+                        };
+                }
+            }
+            *channel = (sum / 27) as u8;
+        }
+        if let SubpixelOrder::Bgr | SubpixelOrder::VBgr = order {
+            pixel.reverse();
+        }
+    }
+    (metrics, data)
+}
+
+/// Samples a glyph rendered at three times the size is moved right by, to put
+/// the left edge of its outline on a pixel boundary
+fn shift(large: &fontdue::Metrics, snap: bool) -> i32 {
+    if !snap || large.width == 0 {
+        return 0;
+    }
+    let edge = large.xmin + (large.bounds.xmin - large.xmin as f32 > 0.5) as i32;
+    (edge + 1).div_euclid(3) * 3 - edge
+}
+
+#[inline]
+fn ceil3(n: i32) -> i32 {
+    -(-n).div_euclid(3)
+}
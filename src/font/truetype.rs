@@ -1,33 +1,277 @@
-use super::{Cow, Font, Glyph, Point};
-use alloc::sync::Arc;
+use super::{
+    subpixel::{self, SubpixelOrder},
+    Cow, Font, FontError, Glyph, Point,
+};
+use alloc::{sync::Arc, vec::Vec};
+
+/// Height of a line of text
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineHeight {
+    /// Multiple of the line spacing recommended by the font
+    Scale(f32),
+    Pixels(usize),
+}
+
+/// Layout of a `TrueTypeFont` on the cell grid and rendering of its glyphs
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TrueTypeSettings {
+    pub line_height: LineHeight,
+    /// Pixels added to (or removed from) the width of every cell
+    pub letter_spacing: isize,
+    /// Pixels the baseline is moved down, the glyphs being otherwise centered
+    /// vertically in the line
+    pub baseline_offset: isize,
+    /// Width of a cell before letter spacing, `None` using the advance of `M` or `0`
+    pub cell_width: Option<usize>,
+    /// LCD subpixel antialiasing, `None` drawing grayscale glyphs
+    pub subpixel: Option<SubpixelOrder>,
+    /// Move every glyph by a fraction of a pixel so the left edge of its outline
+    /// falls on a pixel boundary, making vertical stems sharper
+    pub snap: bool,
+    /// Widen stems by up to one pixel (255), `darken / 255` of the coverage of
+    /// each pixel being added to the pixel on its right
+    pub darken: u8,
+    /// Draw glyphs without antialiasing, pixels covered at least this much being set
+    pub threshold: Option<u8>,
+}
+
+impl Default for TrueTypeSettings {
+    fn default() -> TrueTypeSettings {
+        TrueTypeSettings {
+            line_height: LineHeight::Scale(1.25),
+            letter_spacing: 0,
+            baseline_offset: 0,
+            cell_width: None,
+            subpixel: None,
+            snap: false,
+            darken: 0,
+            threshold: None,
+        }
+    }
+}
 
 pub struct TrueTypeFont {
     inner: fontdue::Font,
     cache: lru::LruCache<char, Glyph>,
-    line_size: usize,
+    settings: TrueTypeSettings,
+    line: fontdue::LineMetrics,
+    baseline: isize,
     height: usize,
     size: f32,
+    cell_width: usize,
 }
 
 impl TrueTypeFont {
-    pub fn new(data: &[u8], size: f32) -> TrueTypeFont {
-        let settings = fontdue::FontSettings {
+    pub fn new(data: &[u8], size: f32) -> Result<TrueTypeFont, FontError> {
+        TrueTypeFont::with_settings(data, size, TrueTypeSettings::default())
+    }
+
+    pub fn with_settings(
+        data: &[u8],
+        size: f32,
+        settings: TrueTypeSettings,
+    ) -> Result<TrueTypeFont, FontError> {
+        let font_settings = fontdue::FontSettings {
             scale: size,
             ..fontdue::FontSettings::default()
         };
-        let font = fontdue::Font::from_bytes(data, settings).unwrap();
-        let line = font
-            .vertical_line_metrics(size)
-            .unwrap_or(font.horizontal_line_metrics(size).unwrap());
-        let height = (line.new_line_size * 1.25) as usize;
-        TrueTypeFont {
+        let font = fontdue::Font::from_bytes(data, font_settings).map_err(FontError::Parse)?;
+        let line = line_metrics(&font, size)?;
+        let mut font = TrueTypeFont {
             inner: font,
             cache: lru::LruCache::new(128),
-            line_size: line.new_line_size as usize,
-            height,
+            settings,
+            line,
+            baseline: 0,
+            height: 0,
             size,
+            cell_width: 0,
+        };
+        font.layout();
+        Ok(font)
+    }
+
+    #[inline]
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Change the pixel size, dropping cached glyphs. Call `Fbterm::relayout` afterwards.
+    pub fn set_size(&mut self, size: f32) -> Result<(), FontError> {
+        self.line = line_metrics(&self.inner, size)?;
+        self.size = size;
+        self.layout();
+        Ok(())
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &TrueTypeSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: TrueTypeSettings) {
+        self.settings = settings;
+        self.layout();
+    }
+
+    /// Take the line and cell metrics of `other`, so both fonts share a grid
+    pub(crate) fn fit(&mut self, other: &TrueTypeFont) {
+        self.height = other.height;
+        self.baseline = other.baseline;
+        self.cell_width = other.cell_width;
+        self.settings.subpixel = other.settings.subpixel;
+        self.settings.snap = other.settings.snap;
+        self.settings.darken = other.settings.darken;
+        self.settings.threshold = other.settings.threshold;
+        self.cache.clear();
+    }
+
+    /// Compute the line and cell metrics from the settings, dropping cached glyphs
+    fn layout(&mut self) {
+        let line = self.line;
+        self.height = match self.settings.line_height {
+            LineHeight::Scale(scale) => (line.new_line_size * scale) as usize,
+            LineHeight::Pixels(height) => height,
         }
+        .max(1);
+        let leading = self.height as f32 - (line.ascent - line.descent);
+        self.baseline =
+            (line.ascent + leading / 2.0 + 0.5) as isize + self.settings.baseline_offset;
+        let cell_width = self
+            .settings
+            .cell_width
+            .unwrap_or_else(|| auto_cell_width(&self.inner, self.size));
+        self.cell_width = (cell_width as isize + self.settings.letter_spacing).max(1) as usize;
+        self.cache.clear();
     }
+
+    /// Subpixel order glyphs are rendered with, monochrome glyphs being grayscale
+    #[inline]
+    fn subpixel_order(&self) -> Option<SubpixelOrder> {
+        self.settings
+            .subpixel
+            .filter(|_| self.settings.threshold.is_none())
+    }
+
+    #[inline]
+    fn channels(&self) -> usize {
+        if self.subpixel_order().is_some() {
+            3
+        } else {
+            1
+        }
+    }
+
+    fn rasterize(&self, index: usize) -> (fontdue::Metrics, Vec<u8>) {
+        let order = self.subpixel_order();
+        let (mut metrics, mut data) = if order.is_some() || self.settings.snap {
+            subpixel::rasterize(&self.inner, index, self.size, order, self.settings.snap)
+        } else {
+            self.inner.rasterize_indexed(index, self.size)
+        };
+        if self.settings.darken > 0 && metrics.width > 0 {
+            data = darken(&metrics, &data, self.channels(), self.settings.darken);
+            metrics.width += 1;
+        }
+        (metrics, data)
+    }
+
+    fn raster_metrics(&self, index: usize) -> fontdue::Metrics {
+        let order = self.subpixel_order();
+        let mut metrics = if order.is_some() || self.settings.snap {
+            let large = self.inner.metrics_indexed(index, self.size * 3.0);
+            subpixel::metrics(&large, order, self.settings.snap)
+        } else {
+            self.inner.metrics_indexed(index, self.size)
+        };
+        if self.settings.darken > 0 && metrics.width > 0 {
+            metrics.width += 1;
+        }
+        metrics
+    }
+
+    /// Center a glyph in its cell, or in two cells if it is a wide glyph,
+    /// clipping what overflows the cell or the line
+    fn glyph(&self, metrics: fontdue::Metrics, data: Option<Vec<u8>>) -> Glyph {
+        let cells = if metrics.advance_width > self.cell_width as f32 * 1.5 {
+            2
+        } else {
+            1
+        };
+        let advance = self.cell_width * cells;
+        let left =
+            metrics.xmin as isize + ((advance as f32 - metrics.advance_width) / 2.0) as isize;
+        let skip = (-left).max(0) as usize;
+        let x = left.max(0) as usize;
+        let width = metrics
+            .width
+            .saturating_sub(skip)
+            .min(advance.saturating_sub(x));
+        let top = self.baseline - metrics.ymin as isize - metrics.height as isize;
+        let skip_rows = (-top).max(0) as usize;
+        let y = top.max(0);
+        let height = metrics
+            .height
+            .saturating_sub(skip_rows)
+            .min((self.height as isize - y).max(0) as usize);
+        let channels = self.channels();
+        let data = match data {
+            Some(data) if width > 0 && height > 0 => Cow::Arc(
+                data.chunks(metrics.width * channels)
+                    .skip(skip_rows)
+                    .take(height)
+                    .flat_map(|row| &row[skip * channels..(skip + width) * channels])
+                    .copied()
+                    .collect(),
+            ),
+            Some(_) => Cow::Arc(Arc::from(Vec::new())),
+            None => Cow::none(),
+        };
+        Glyph {
+            data,
+            width,
+            advance,
+            height,
+            x,
+            y,
+        }
+    }
+}
+
+/// Add `amount / 255` of the coverage of every pixel to the pixel on its right,
+/// returning a bitmap one pixel wider
+fn darken(metrics: &fontdue::Metrics, data: &[u8], channels: usize, amount: u8) -> Vec<u8> {
+    let width = metrics.width * channels;
+    let mut darkened = Vec::with_capacity((width + channels) * metrics.height);
+    for row in data.chunks(width) {
+        for i in 0..width + channels {
+            let cov = row.get(i).copied().unwrap_or(0) as u32;
+            let left = i
+                .checked_sub(channels)
+                .and_then(|i| row.get(i))
+                .copied()
+                .unwrap_or(0) as u32;
+            darkened.push((cov + left * amount as u32 / 255).min(255) as u8);
+        }
+    }
+    darkened
+}
+
+fn line_metrics(font: &fontdue::Font, size: f32) -> Result<fontdue::LineMetrics, FontError> {
+    if !(size > 0.0 && size.is_finite()) {
+        return Err(FontError::InvalidSize);
+    }
+    font.horizontal_line_metrics(size)
+        .or_else(|| font.vertical_line_metrics(size))
+        .ok_or(FontError::MissingLineMetrics)
+}
+
+fn auto_cell_width(font: &fontdue::Font, size: f32) -> usize {
+    let width = ['M', '0']
+        .iter()
+        .find(|&&c| font.lookup_glyph_index(c) != 0)
+        .map_or(size / 2.0, |&c| font.metrics(c, size).advance_width);
+    ((width + 0.5) as usize).max(1)
 }
 
 impl Font for TrueTypeFont {
@@ -36,23 +280,22 @@ impl Font for TrueTypeFont {
         self.height
     }
 
+    #[inline]
+    fn cell_width(&self) -> usize {
+        self.cell_width
+    }
+
     fn get_glyph(&mut self, c: char) -> Option<Glyph> {
+        let index = self.inner.lookup_glyph_index(c);
+        if index == 0 {
+            return None;
+        }
         let cache = self.cache.get(&c);
         match cache {
             Some(glyph) => glyph.clone().into(),
             None => {
-                let (metrics, data) = self.inner.rasterize(c, self.size);
-                assert!(metrics.xmin >= 0);
-                assert!(metrics.xmin as usize + metrics.width <= metrics.advance_width as usize);
-                let data = Cow::Arc(Arc::from(data));
-                let glyph = Glyph {
-                    data,
-                    width: metrics.width,
-                    advance: metrics.advance_width as usize,
-                    height: metrics.height,
-                    x: metrics.xmin as usize,
-                    y: self.line_size as isize - metrics.ymin as isize - metrics.height as isize,
-                };
+                let (metrics, data) = self.rasterize(index);
+                let glyph = self.glyph(metrics, Some(data));
                 self.cache.put(c, glyph.clone());
                 Some(glyph)
             }
@@ -61,31 +304,37 @@ impl Font for TrueTypeFont {
 
     #[inline]
     fn metrics(&self, c: char) -> Option<Glyph> {
+        let index = self.inner.lookup_glyph_index(c);
+        if index == 0 {
+            return None;
+        }
         let cache = self.cache.peek(&c);
         match cache {
             Some(glyph) => Some(Glyph {
                 data: Cow::none(),
                 ..*glyph
             }),
-            None => {
-                let metrics = self.inner.metrics(c, self.size);
-                assert!(metrics.xmin >= 0);
-                assert!(metrics.xmin as usize + metrics.width <= metrics.advance_width as usize);
-                let glyph = Glyph {
-                    data: Cow::none(),
-                    width: metrics.width,
-                    advance: metrics.advance_width as usize,
-                    height: metrics.height,
-                    x: metrics.xmin as usize,
-                    y: self.line_size as isize - metrics.ymin as isize - metrics.height as isize,
-                };
-                Some(glyph)
-            }
+            None => Some(self.glyph(self.raster_metrics(index), None)),
         }
     }
 
     #[inline]
     fn get_pixel(&self, glyph: &Glyph, x: usize, y: usize) -> Point {
-        Point::Coverage(glyph.data[y * glyph.width + x])
+        let index = (y * glyph.width + x) * self.channels();
+        if let Some(threshold) = self.settings.threshold {
+            return Point::Bit(glyph.data.get(index).is_some_and(|&cov| cov >= threshold));
+        }
+        if self.channels() == 1 {
+            return Point::Coverage(glyph.data.get(index).copied().unwrap_or(0));
+        }
+        match glyph.data.get(index..index + 3) {
+            Some(&[r, g, b]) => Point::SubpixelCoverage(r, g, b),
+            _ => Point::SubpixelCoverage(0, 0, 0),
+        }
+    }
+
+    fn notdef(&mut self) -> Option<Glyph> {
+        let (metrics, data) = self.rasterize(0);
+        Some(self.glyph(metrics, Some(data)))
     }
 }
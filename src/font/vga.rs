@@ -1,8 +1,7 @@
-use super::{Font, Glyph, Point, Cow};
+use super::{bitmap::BitmapFont, Font, Glyph, Point};
 
 pub struct VGAFont {
-    pub(crate) buffer: &'static [u8],
-    pub(crate) height: usize,
+    inner: BitmapFont,
 }
 
 pub enum VGAFontConfig {
@@ -13,70 +12,45 @@ pub enum VGAFontConfig {
 
 impl VGAFont {
     pub fn new(font: VGAFontConfig) -> VGAFont {
-        match font {
-            VGAFontConfig::VGA8x8 => VGAFont {
-                buffer: &VGAFONT8,
-                height: 8,
-            },
-            VGAFontConfig::VGA8x14 => VGAFont {
-                buffer: &VGAFONT14,
-                height: 14,
-            },
-            VGAFontConfig::VGA8x16 => VGAFont {
-                buffer: &VGAFONT16,
-                height: 16,
-            },
-        }
+        let inner = match font {
+            VGAFontConfig::VGA8x8 => BitmapFont::new(&VGAFONT8, 8, 8),
+            VGAFontConfig::VGA8x14 => BitmapFont::new(&VGAFONT14, 8, 14),
+            VGAFontConfig::VGA8x16 => BitmapFont::new(&VGAFONT16, 8, 16),
+        };
+        VGAFont { inner }
     }
-    #[inline]
-    fn width(&self) -> usize {
-        8
+}
+
+impl From<VGAFont> for BitmapFont {
+    fn from(font: VGAFont) -> BitmapFont {
+        font.inner
     }
 }
 
 impl Font for VGAFont {
     #[inline]
     fn height(&self) -> usize {
-        self.height
+        self.inner.height()
+    }
+
+    #[inline]
+    fn cell_width(&self) -> usize {
+        self.inner.cell_width()
     }
 
     #[inline]
     fn get_glyph(&mut self, c: char) -> Option<Glyph> {
-        if c as usize > 256 {
-            return None;
-        }
-        let data =
-            self.buffer[((c as usize) * self.height())..((c as usize + 1) * self.height())].into();
-        Some(Glyph {
-            data,
-            width: self.width(),
-            advance: self.width(),
-            height: self.height(),
-            x: 0,
-            y: 0,
-        })
+        self.inner.get_glyph(c)
     }
 
     #[inline]
     fn metrics(&self, c: char) -> Option<Glyph> {
-        if c as usize > 256 {
-            return None;
-        }
-        Some(Glyph {
-            data: Cow::none(),
-            width: self.width(),
-            advance: self.width(),
-            height: self.height(),
-            x: 0,
-            y: 0,
-        })
+        self.inner.metrics(c)
     }
 
     #[inline]
     fn get_pixel(&self, glyph: &Glyph, x: usize, y: usize) -> Point {
-        assert!(y < self.height());
-        assert!(x < self.width());
-        Point::Bit(((glyph.data[y] >> (7 - x)) & 0x1) == 0x1)
+        self.inner.get_pixel(glyph, x, y)
     }
 }
 
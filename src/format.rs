@@ -0,0 +1,266 @@
+/// Layout of a pixel only known at runtime, as described by the firmware
+/// (UEFI GOP bitmasks, multiboot2 or Linux fbdev fields)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PixelFormat {
+    bytes: usize,
+    red: Channel,
+    green: Channel,
+    blue: Channel,
+    reserved: Channel,
+    big_endian: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Channel {
+    shift: u32,
+    size: u32,
+}
+
+impl Channel {
+    const fn from_mask(mask: u32) -> Channel {
+        if mask == 0 {
+            return Channel { shift: 0, size: 0 };
+        }
+        Channel {
+            shift: mask.trailing_zeros(),
+            size: (mask >> mask.trailing_zeros()).trailing_ones(),
+        }
+    }
+
+    #[inline]
+    fn pack(self, value: u8) -> u32 {
+        // the value is repeated to fill wider channels, so that 255 packs to all ones
+        let (mut repeated, mut bits) = (0u32, 0);
+        while bits < self.size {
+            repeated = repeated << 8 | value as u32;
+            bits += 8;
+        }
+        let value = repeated >> (bits - self.size);
+        value.checked_shl(self.shift).unwrap_or(0)
+    }
+
+    #[inline]
+    fn unpack(self, raw: u32) -> u8 {
+        if self.size == 0 {
+            return 0;
+        }
+        let max = u32::MAX >> (32 - self.size.min(32));
+        let value = raw.checked_shr(self.shift).unwrap_or(0) & max;
+        if self.size >= 8 {
+            (value >> (self.size - 8)) as u8
+        } else {
+            (value * 255 / max) as u8
+        }
+    }
+}
+
+impl PixelFormat {
+    /// Channels given as bitmasks of a pixel of 8, 15, 16, 24 or 32 bits, `None`
+    /// for other depths. The alpha of the colors is written to the reserved bits.
+    pub const fn from_masks(
+        bits_per_pixel: usize,
+        red: u32,
+        green: u32,
+        blue: u32,
+        reserved: u32,
+    ) -> Option<PixelFormat> {
+        let bytes = match bits_per_pixel {
+            8 => 1,
+            15 | 16 => 2,
+            24 => 3,
+            32 => 4,
+            _ => return None,
+        };
+        Some(PixelFormat {
+            bytes,
+            red: Channel::from_mask(red),
+            green: Channel::from_mask(green),
+            blue: Channel::from_mask(blue),
+            reserved: Channel::from_mask(reserved),
+            big_endian: false,
+        })
+    }
+
+    /// Channels given as (position, size) in bits
+    pub const fn from_fields(
+        bits_per_pixel: usize,
+        red: (u8, u8),
+        green: (u8, u8),
+        blue: (u8, u8),
+        reserved: (u8, u8),
+    ) -> Option<PixelFormat> {
+        PixelFormat::from_masks(
+            bits_per_pixel,
+            mask(red),
+            mask(green),
+            mask(blue),
+            mask(reserved),
+        )
+    }
+
+    /// Store pixels with their most significant byte first
+    pub const fn big_endian(mut self) -> PixelFormat {
+        self.big_endian = true;
+        self
+    }
+
+    #[inline]
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.bytes
+    }
+
+    #[inline]
+    pub fn pack(&self, (r, g, b, a): (u8, u8, u8, u8)) -> u32 {
+        self.red.pack(r) | self.green.pack(g) | self.blue.pack(b) | self.reserved.pack(a)
+    }
+
+    #[inline]
+    pub fn unpack(&self, raw: u32) -> (u8, u8, u8, u8) {
+        (
+            self.red.unpack(raw),
+            self.green.unpack(raw),
+            self.blue.unpack(raw),
+            self.reserved.unpack(raw),
+        )
+    }
+
+    /// Write a packed pixel at `ptr`, which doesn't need to be aligned
+    #[inline]
+    pub(crate) unsafe fn write(&self, ptr: *mut u8, raw: u32) {
+        match self.bytes {
+            4 if (ptr as usize).is_multiple_of(4) => {
+                let raw = if self.big_endian {
+                    raw.to_be()
+                } else {
+                    raw.to_le()
+                };
+                (ptr as *mut u32).write_volatile(raw)
+            }
+            2 if (ptr as usize).is_multiple_of(2) => {
+                let raw = raw as u16;
+                let raw = if self.big_endian {
+                    raw.to_be()
+                } else {
+                    raw.to_le()
+                };
+                (ptr as *mut u16).write_volatile(raw)
+            }
+            bytes => {
+                for i in 0..bytes {
+                    let shift = if self.big_endian { bytes - 1 - i } else { i } * 8;
+                    ptr.add(i).write_volatile((raw >> shift) as u8)
+                }
+            }
+        }
+    }
+
+    /// Read a packed pixel at `ptr`, which doesn't need to be aligned
+    #[inline]
+    pub(crate) unsafe fn read(&self, ptr: *const u8) -> u32 {
+        match self.bytes {
+            4 if (ptr as usize).is_multiple_of(4) => {
+                let raw = (ptr as *const u32).read_volatile();
+                if self.big_endian {
+                    u32::from_be(raw)
+                } else {
+                    u32::from_le(raw)
+                }
+            }
+            2 if (ptr as usize).is_multiple_of(2) => {
+                let raw = (ptr as *const u16).read_volatile();
+                (if self.big_endian {
+                    u16::from_be(raw)
+                } else {
+                    u16::from_le(raw)
+                }) as u32
+            }
+            bytes => (0..bytes).fold(0, |raw, i| {
+                let shift = if self.big_endian { bytes - 1 - i } else { i } * 8;
+                raw | (ptr.add(i).read_volatile() as u32) << shift
+            }),
+        }
+    }
+}
+
+const fn mask((position, size): (u8, u8)) -> u32 {
+    if size == 0 || position >= 32 {
+        return 0;
+    }
+    let bits = if size >= 32 {
+        u32::MAX
+    } else {
+        (1 << size) - 1
+    };
+    bits << position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLORS: [(u8, u8, u8, u8); 4] = [
+        (0, 0, 0, 255),
+        (255, 255, 255, 255),
+        (255, 0, 0, 255),
+        (0x12, 0x80, 0xfe, 255),
+    ];
+
+    /// `color` written as `format` and read back, with the bytes in memory
+    fn round_trip(format: &PixelFormat, color: (u8, u8, u8, u8)) -> ((u8, u8, u8, u8), [u8; 4]) {
+        let mut bytes = [0u8; 4];
+        unsafe {
+            format.write(bytes.as_mut_ptr(), format.pack(color));
+            (format.unpack(format.read(bytes.as_ptr())), bytes)
+        }
+    }
+
+    #[test]
+    fn rgb565() {
+        let format = PixelFormat::from_masks(16, 0xf800, 0x07e0, 0x001f, 0).unwrap();
+        assert_eq!(format.pack((255, 255, 255, 255)), 0xffff);
+        assert_eq!(format.pack((0, 255, 0, 255)), 0x07e0);
+        for color in &COLORS[..3] {
+            assert_eq!(
+                round_trip(&format, *color).0,
+                (color.0, color.1, color.2, 0)
+            );
+        }
+        assert_eq!(round_trip(&format, (0, 0, 255, 0)).1, [0x1f, 0, 0, 0]);
+    }
+
+    #[test]
+    fn rgb888() {
+        let format = PixelFormat::from_masks(24, 0xff0000, 0xff00, 0xff, 0).unwrap();
+        for color in COLORS {
+            assert_eq!(round_trip(&format, color).0, (color.0, color.1, color.2, 0));
+        }
+        assert_eq!(round_trip(&format, COLORS[3]).1, [0xfe, 0x80, 0x12, 0]);
+        let format = format.big_endian();
+        assert_eq!(
+            round_trip(&format, COLORS[3]),
+            ((0x12, 0x80, 0xfe, 0), [0x12, 0x80, 0xfe, 0])
+        );
+    }
+
+    #[test]
+    fn xrgb8888_big_endian() {
+        let format = PixelFormat::from_masks(32, 0xff0000, 0xff00, 0xff, 0xff000000)
+            .unwrap()
+            .big_endian();
+        for color in COLORS {
+            assert_eq!(round_trip(&format, color).0, color);
+        }
+        assert_eq!(round_trip(&format, COLORS[3]).1, [255, 0x12, 0x80, 0xfe]);
+    }
+
+    #[test]
+    fn wide_channels() {
+        // 2:10:10:10, as some UEFI GOPs report
+        let format = PixelFormat::from_masks(32, 0x3ff00000, 0xffc00, 0x3ff, 0xc0000000).unwrap();
+        assert_eq!(format.pack((255, 255, 255, 255)), 0xffffffff);
+        assert_eq!(format.pack((0x80, 0, 0, 0)), 0x202 << 20);
+        for v in 0..=255 {
+            assert_eq!(round_trip(&format, (v, v, v, 255)).0, (v, v, v, 255));
+        }
+    }
+}
@@ -3,32 +3,78 @@
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+mod cell;
+mod damage;
 mod fb;
 mod font;
+mod format;
+mod gamma;
 mod num;
+mod palette;
 mod pixel;
 
 #[cfg(feature = "alloc")]
-use alloc::{collections::VecDeque, string::String};
+use alloc::{collections::VecDeque, vec::Vec};
+pub use cell::Cell;
+#[cfg(not(feature = "alloc"))]
+use cell::CellRing;
+pub use damage::{Damage, DAMAGE_RECTS};
+pub use fb::Blending;
+pub use fb::FbError;
 pub use fb::Framebuffer;
 pub use fb::Rect;
 #[cfg(feature = "alloc")]
-pub use font::truetype::TrueTypeFont;
 pub use font::{
+    bdf::BdfFont,
+    fallback::FallbackFont,
+    family::FontFamily,
+    subpixel::SubpixelOrder,
+    truetype::{LineHeight, TrueTypeFont, TrueTypeSettings},
+};
+pub use font::{
+    bitmap::{BitOrder, BitmapFont},
+    missing::MissingGlyph,
     vga::{VGAFont, VGAFontConfig},
-    Font, Glyph, Point,
+    Font, FontError, Glyph, Point, Style,
 };
+use font::{
+    missing::HexBox,
+    procedural::{self, CellGlyph},
+};
+pub use format::PixelFormat;
 use num::Saturating;
+pub use palette::{Palette, PaletteHook};
 pub use pixel::*;
 
+/// Include a font written by `fbterm_embed::Embed::write` from a build script,
+/// evaluating to a `BitmapFont` usable in a `static`
+#[macro_export]
+macro_rules! include_font {
+    ($name:expr) => {{
+        use $crate::BitmapFont;
+        include!(concat!(env!("OUT_DIR"), "/", $name, ".rs"))
+    }};
+}
+
 pub struct Fbterm<'a, P: Pixel, F: Font> {
     pub framebuffer: Framebuffer<'a, P>,
     font: F,
     x: Saturating,
     y: Saturating,
-    dirty: Option<Rect>,
+    dirty: Damage,
+    missing: MissingGlyph,
+    procedural: bool,
+    style: Style,
+    #[cfg(feature = "alloc")]
+    lines: VecDeque<Vec<Cell<P>>>,
+    /// Whether each of `lines` continues the one before, wrapped at the right edge
     #[cfg(feature = "alloc")]
-    lines: VecDeque<String>,
+    wrapped: VecDeque<bool>,
+    /// Cell size the cursor position was laid out with
+    #[cfg(not(feature = "alloc"))]
+    cell: (usize, usize),
+    #[cfg(not(feature = "alloc"))]
+    history: CellRing<'a, P>,
 }
 
 impl<'a, P: Pixel, F: Font> Fbterm<'a, P, F> {
@@ -37,19 +83,32 @@ impl<'a, P: Pixel, F: Font> Fbterm<'a, P, F> {
         let height = framebuffer.height();
         #[cfg(feature = "alloc")]
         let lines = {
-            let lines_len = height / font.height();
+            let lines_len = height / font.height().max(1);
             let mut lines = VecDeque::with_capacity(lines_len);
-            lines.push_back(String::new());
+            lines.push_back(Vec::new());
             lines
         };
+        #[cfg(feature = "alloc")]
+        let wrapped = VecDeque::from([false]);
         Fbterm {
             framebuffer,
-            font,
-            x: Saturating::new(width - 1),
-            y: Saturating::new(height - 1),
-            dirty: None,
+            // the cursor may reach the right and bottom edges, a glyph ending
+            // there still fitting
+            x: Saturating::new(width),
+            y: Saturating::new(height),
+            dirty: Damage::new(),
+            missing: MissingGlyph::default(),
+            procedural: true,
+            style: Style::Regular,
+            #[cfg(not(feature = "alloc"))]
+            cell: (font.cell_width().max(1), font.height().max(1)),
+            #[cfg(not(feature = "alloc"))]
+            history: CellRing::new(&mut []),
             #[cfg(feature = "alloc")]
             lines,
+            #[cfg(feature = "alloc")]
+            wrapped,
+            font,
         }
     }
 
@@ -58,20 +117,28 @@ impl<'a, P: Pixel, F: Font> Fbterm<'a, P, F> {
         self.y.set(0);
         self.framebuffer.clear();
         self.framebuffer.flush(None);
-        self.dirty = None;
+        self.dirty.clear();
         #[cfg(feature = "alloc")]
         {
             self.lines.clear();
-            self.lines.push_back(String::new());
+            self.lines.push_back(Vec::new());
+            self.wrapped.clear();
+            self.wrapped.push_back(false);
         }
+        #[cfg(not(feature = "alloc"))]
+        self.history.clear();
+    }
+
+    /// Keep the characters printed from now on in `cells`, so that `relayout` can
+    /// draw them again without `alloc`. The oldest are dropped once it is full, it
+    /// should hold at least `columns() * rows()` cells.
+    #[cfg(not(feature = "alloc"))]
+    pub fn set_cells(&mut self, cells: &'a mut [Cell<P>]) {
+        self.history = CellRing::new(cells);
     }
 
     pub fn flush(&mut self) {
-        if self.dirty.is_none() {
-            return;
-        }
-        self.framebuffer.flush(self.dirty);
-        self.dirty = None;
+        self.framebuffer.flush_damage(&mut self.dirty);
     }
 
     pub fn putc(&mut self, c: char) {
@@ -84,31 +151,47 @@ impl<'a, P: Pixel, F: Font> Fbterm<'a, P, F> {
                     self.scroll();
                 }
                 #[cfg(feature = "alloc")]
-                self.lines.push_back(String::new());
+                {
+                    self.lines.push_back(Vec::new());
+                    self.wrapped.push_back(false);
+                }
+                #[cfg(not(feature = "alloc"))]
+                self.history.push(self.new_cell('\n'));
             }
             '\r' => {
                 self.x.set(0);
                 // FIXME: should \r drop all char ?
                 #[cfg(feature = "alloc")]
                 self.lines.back_mut().unwrap().clear();
+                #[cfg(not(feature = "alloc"))]
+                self.history.clear_line();
             }
             '\t' => {
                 self.print("    ");
             }
-            #[cfg(feature = "alloc")]
             '\u{08}' => {
-                let last_line = self.lines.back_mut().unwrap();
-                let last_char = last_line.pop();
-                match last_char {
-                    Some(c) => {
-                        let metrics = self.font.metrics(c).unwrap();
-                        self.x -= metrics.advance;
-                        let basex = *self.x + metrics.x;
-                        let basey = (*self.y as isize + metrics.y) as usize;
-                        let clean = Rect::new(basex, basey, metrics.width, metrics.height);
-                        self.framebuffer
-                            .draw_rect(clean, self.framebuffer.get_background());
-                        self.add_dirty(clean);
+                #[cfg(feature = "alloc")]
+                let last_cell = self.lines.back_mut().unwrap().pop();
+                #[cfg(not(feature = "alloc"))]
+                let last_cell = self.history.pop();
+                match last_cell {
+                    Some(Cell { c, style, .. }) => {
+                        let shape = self.shape(c, style);
+                        self.x -= shape.advance();
+                        let clean = match shape {
+                            Shape::Glyph(glyph) => {
+                                let basex = *self.x + glyph.x;
+                                let top = *self.y as isize + glyph.y;
+                                let skip = (-top).max(0) as usize;
+                                let height = glyph.height.saturating_sub(skip);
+                                Rect::new(basex, top.max(0) as usize, glyph.width, height)
+                            }
+                            _ => Rect::new(*self.x, *self.y, shape.advance(), self.font.height()),
+                        };
+                        let background = self.framebuffer.get_background();
+                        if self.framebuffer.try_draw_rect(clean, background).is_ok() {
+                            self.add_dirty(clean);
+                        }
                     }
                     None => {
                         // FIXME: deal with line change
@@ -116,24 +199,28 @@ impl<'a, P: Pixel, F: Font> Fbterm<'a, P, F> {
                 }
             }
             _ => {
-                let (c, glyph) = match self.font.get_glyph(c) {
-                    Some(g) => (c, g),
-                    None => (' ', self.font.get_glyph(' ').unwrap()),
-                };
-                let (mut next_x, overflow) = self.x.add_check(glyph.advance);
+                let shape = self.shape(c, self.style);
+                let advance = shape.advance();
+                let (mut next_x, overflow) = self.x.add_check(advance);
                 if overflow {
                     self.x.set(0);
-                    next_x = glyph.advance;
+                    next_x = advance;
                     self.y += self.font.height();
                     if self.y.add_check(self.font.height()).1 {
                         self.scroll();
                     }
                     #[cfg(feature = "alloc")]
-                    self.lines.push_back(String::new());
+                    {
+                        self.lines.push_back(Vec::new());
+                        self.wrapped.push_back(true);
+                    }
                 }
+                let cell = self.new_cell(c);
                 #[cfg(feature = "alloc")]
-                self.lines.back_mut().unwrap().push(c);
-                self.draw_glyph(glyph);
+                self.lines.back_mut().unwrap().push(cell);
+                #[cfg(not(feature = "alloc"))]
+                self.history.push(cell);
+                self.draw_shape(shape);
                 self.x.set(next_x);
             }
         }
@@ -146,6 +233,25 @@ impl<'a, P: Pixel, F: Font> Fbterm<'a, P, F> {
         self.flush()
     }
 
+    #[inline]
+    pub fn set_missing_glyph(&mut self, missing: MissingGlyph) {
+        self.missing = missing
+    }
+
+    /// Draw box drawing, block elements, braille and Powerline separators
+    /// procedurally instead of with the font (enabled by default)
+    #[inline]
+    pub fn set_procedural_glyphs(&mut self, enable: bool) {
+        self.procedural = enable
+    }
+
+    /// Face the next characters are drawn with, for fonts having several faces
+    /// like `FontFamily`
+    #[inline]
+    pub fn set_style(&mut self, style: Style) {
+        self.style = style
+    }
+
     pub fn get_font(&self) -> &F {
         &self.font
     }
@@ -154,34 +260,116 @@ impl<'a, P: Pixel, F: Font> Fbterm<'a, P, F> {
         &mut self.font
     }
 
-    pub fn change_font<T: Font>(mut self, font: T) -> Fbterm<'a, P, T> {
-        #[cfg(feature = "alloc")]
-        let lines = core::mem::replace(&mut self.lines, VecDeque::new());
-        self.clear();
-        let mut term = Fbterm::new(self.framebuffer, font);
+    /// Replace the font by one of the same type, returning the old one
+    pub fn set_font(&mut self, font: F) -> F {
+        let old = core::mem::replace(&mut self.font, font);
+        self.relayout();
+        old
+    }
+
+    /// Replace the font by one of another type
+    pub fn change_font<T: Font>(self, font: T) -> Fbterm<'a, P, T> {
+        let mut term = Fbterm {
+            framebuffer: self.framebuffer,
+            font,
+            x: self.x,
+            y: self.y,
+            dirty: self.dirty,
+            missing: self.missing,
+            procedural: self.procedural,
+            style: self.style,
+            #[cfg(feature = "alloc")]
+            lines: self.lines,
+            #[cfg(feature = "alloc")]
+            wrapped: self.wrapped,
+            #[cfg(not(feature = "alloc"))]
+            cell: self.cell,
+            #[cfg(not(feature = "alloc"))]
+            history: self.history,
+        };
+        term.relayout();
+        term
+    }
+
+    /// Lay the screen out again after the metrics of the font changed, e.g. after
+    /// `TrueTypeFont::set_size` through `get_font_mut`.
+    ///
+    /// The text is drawn again with its colors and styles, leaving the cursor after it.
+    /// Without `alloc` the text is only kept in the cells given to `set_cells`; with
+    /// none, the screen is cleared and the cursor stays in the same cell.
+    pub fn relayout(&mut self) {
         #[cfg(feature = "alloc")]
         {
-            for line in lines {
-                term.print(&line);
-                term.putc('\n');
+            let lines = core::mem::take(&mut self.lines);
+            let wrapped = core::mem::take(&mut self.wrapped);
+            self.clear();
+            // wrapped lines are joined to flow on the new grid
+            let newline = self.new_cell('\n');
+            let lines = lines.iter().zip(wrapped).enumerate();
+            self.replay(lines.flat_map(|(i, (line, wrapped))| {
+                let newline = if i > 0 && !wrapped {
+                    Some(newline)
+                } else {
+                    None
+                };
+                newline.into_iter().chain(line.iter().copied())
+            }));
+        }
+        #[cfg(not(feature = "alloc"))]
+        {
+            let (column, row) = (*self.x / self.cell.0, *self.y / self.cell.1);
+            // printing again must not record the cells a second time
+            let history = core::mem::replace(&mut self.history, CellRing::new(&mut []));
+            self.clear();
+            self.cell = (self.font.cell_width().max(1), self.font.height().max(1));
+            if history.capacity() == 0 {
+                let column = column.min(self.columns().saturating_sub(1));
+                let row = row.min(self.rows().saturating_sub(1));
+                self.x.set(column * self.cell.0);
+                self.y.set(row * self.cell.1);
+            } else {
+                self.replay((0..history.len()).map(|i| history.get(i)));
             }
+            self.history = history;
         }
-        term
     }
 
-    fn add_dirty(&mut self, new: Rect) {
-        if self.dirty.is_none() {
-            self.dirty = Some(new);
-            return;
+    /// Print `cells` again with the colors they were drawn with
+    fn replay<I: Iterator<Item = Cell<P>>>(&mut self, cells: I) {
+        let foreground = self.framebuffer.get_foreground();
+        let background = self.framebuffer.get_background();
+        let style = self.style;
+        for cell in cells {
+            // setting colors rebuilds the blend table of indexed pixels
+            if !same(cell.foreground, self.framebuffer.get_foreground()) {
+                self.framebuffer.set_foreground(cell.foreground);
+            }
+            if !same(cell.background, self.framebuffer.get_background()) {
+                self.framebuffer.set_background(cell.background);
+            }
+            self.style = cell.style;
+            self.putc(cell.c);
         }
-        let old = self.dirty.unwrap();
-        let x = old.left().min(new.left());
-        let y = old.top().min(new.top());
-        let right = old.right().max(new.right());
-        let bottom = old.bottom().max(new.bottom());
-        let width = right - x;
-        let height = bottom - y;
-        self.dirty = Some(Rect::new(x, y, width, height))
+        self.framebuffer.set_foreground(foreground);
+        self.framebuffer.set_background(background);
+        self.style = style;
+        self.flush();
+    }
+
+    /// `c` with the colors and style it is printed with now
+    #[inline]
+    fn new_cell(&self, c: char) -> Cell<P> {
+        Cell {
+            c,
+            foreground: self.framebuffer.get_foreground(),
+            background: self.framebuffer.get_background(),
+            style: self.style,
+        }
+    }
+
+    #[inline]
+    fn add_dirty(&mut self, new: Rect) {
+        self.dirty.add(new)
     }
 
     #[inline]
@@ -194,59 +382,89 @@ impl<'a, P: Pixel, F: Font> Fbterm<'a, P, F> {
         self.framebuffer.height()
     }
 
+    /// Number of character cells in a row
+    #[inline]
+    pub fn columns(&self) -> usize {
+        self.width() / self.font.cell_width().max(1)
+    }
+
+    #[inline]
+    pub fn rows(&self) -> usize {
+        self.height() / self.font.height().max(1)
+    }
+
     #[cfg(feature = "alloc")]
     #[inline]
-    pub fn lines(&self) -> &VecDeque<String> {
+    pub fn lines(&self) -> &VecDeque<Vec<Cell<P>>> {
         &self.lines
     }
 
-    fn draw_glyph(&mut self, glyph: Glyph) {
-        let basex = *self.x + glyph.x;
-        let basey = *self.y as isize + glyph.y;
-        assert!(glyph.y >= 0);
-        let basey = basey as usize;
-        assert!(
-            basex + glyph.width <= self.width(),
-            "x is overflow: {} + {}",
-            basex,
-            glyph.width
-        );
-        assert!(
-            basey + glyph.height <= self.height(),
-            "y is overflow: {} + {}",
-            basey,
-            glyph.height
-        );
-        for y in 0..glyph.height {
-            for x in 0..glyph.width {
-                match self.font.get_pixel(&glyph, x, y) {
-                    font::Point::Bit(bit) => unsafe {
-                        self.framebuffer.draw_bit(basex + x, basey + y, bit)
-                    },
-                    font::Point::Coverage(cov) => unsafe {
-                        self.framebuffer.draw_alpha(basex + x, basey + y, cov)
-                    },
-                };
-            }
+    fn shape(&mut self, c: char, style: Style) -> Shape {
+        let height = self.font.height();
+        if self.procedural && procedural::covers(c) {
+            return Shape::Cell(CellGlyph::new(c, self.font.cell_width(), height));
+        }
+        if let Some(glyph) = self.font.get_styled_glyph(c, style) {
+            return Shape::Glyph(glyph);
+        }
+        let glyph = match self.missing {
+            MissingGlyph::HexBox => None,
+            MissingGlyph::Notdef => self.font.notdef(),
+            MissingGlyph::Space => self.font.get_glyph(' '),
+        };
+        match glyph {
+            Some(glyph) => Shape::Glyph(glyph),
+            None => Shape::HexBox(HexBox::new(c, self.font.cell_width(), height)),
         }
-        self.add_dirty(Rect::new(basex, basey, glyph.width, glyph.height));
+    }
+
+    fn draw_shape(&mut self, shape: Shape) {
+        let (x, y) = (*self.x, *self.y as isize);
+        let font = &self.font;
+        let framebuffer = &mut self.framebuffer;
+        let rect = match &shape {
+            Shape::Glyph(glyph) => draw_points(
+                framebuffer,
+                x + glyph.x,
+                y + glyph.y,
+                glyph.width,
+                glyph.height,
+                |x, y| font.get_pixel(glyph, x, y),
+            ),
+            Shape::Cell(cell) => {
+                draw_points(framebuffer, x, y, cell.width(), cell.height(), |x, y| {
+                    cell.point(x, y)
+                })
+            }
+            Shape::HexBox(hex) => {
+                draw_points(framebuffer, x, y, hex.width(), hex.height(), |x, y| {
+                    Point::Bit(hex.pixel(x, y))
+                })
+            }
+        };
+        self.add_dirty(rect);
     }
 
     /* FIXME: This is too slow */
     fn scroll(&mut self) {
-        let diff = *self.y + self.font.height() + 1 - self.height();
-        self.framebuffer.copy_rect(
-            Rect::new(0, diff, self.framebuffer.width(), self.height() - diff),
-            Rect::new(0, 0, self.framebuffer.width(), self.height() - diff),
-        );
-        self.framebuffer.draw_rect(
-            Rect::new(0, self.height() - diff, self.framebuffer.width(), diff),
-            self.framebuffer.get_background(),
-        );
+        // a line taller than the screen scrolls everything out
+        let diff = (*self.y + self.font.height() - self.height()).min(self.height());
+        let (width, height) = (self.width(), self.height());
+        if diff < height {
+            let src = Rect::new(0, diff, width, height - diff);
+            let dst = Rect::new(0, 0, width, height - diff);
+            self.framebuffer.try_copy_rect(src, dst).ok();
+        }
+        let background = self.framebuffer.get_background();
+        let rest = Rect::new(0, height - diff, width, diff);
+        self.framebuffer.try_draw_rect(rest, background).ok();
         self.y -= diff;
         self.add_dirty(Rect::new(0, 0, self.width(), self.height()));
         #[cfg(feature = "alloc")]
-        self.lines.pop_front();
+        {
+            self.lines.pop_front();
+            self.wrapped.pop_front();
+        }
     }
 
     /*
@@ -257,6 +475,83 @@ impl<'a, P: Pixel, F: Font> Fbterm<'a, P, F> {
     */
 }
 
+/// What is drawn for one character
+enum Shape {
+    Glyph(Glyph),
+    Cell(CellGlyph),
+    HexBox(HexBox),
+}
+
+impl Shape {
+    fn advance(&self) -> usize {
+        match self {
+            Shape::Glyph(glyph) => glyph.advance,
+            Shape::Cell(cell) => cell.width(),
+            Shape::HexBox(hex) => hex.width(),
+        }
+    }
+}
+
+/// Whether two pixels draw the same color
+#[inline]
+fn same<P: Pixel>(a: P, b: P) -> bool {
+    a.index() == b.index() && a.get() == b.get()
+}
+
+fn draw_points<P: Pixel>(
+    framebuffer: &mut Framebuffer<P>,
+    basex: usize,
+    basey: isize,
+    width: usize,
+    height: usize,
+    point: impl Fn(usize, usize) -> Point,
+) -> Rect {
+    // glyphs overhanging the framebuffer are clipped
+    let skip = (-basey).max(0) as usize;
+    let basey = basey.max(0) as usize;
+    let width = width.min(framebuffer.width().saturating_sub(basex));
+    let height = height
+        .saturating_sub(skip)
+        .min(framebuffer.height().saturating_sub(basey));
+    for y in 0..height {
+        // runs of up to 8 bits are drawn together, a byte at a time on 1 bit framebuffers
+        let (mut bits, mut count) = (0u8, 0);
+        for x in 0..width {
+            let point = point(x, y + skip);
+            if let Point::Bit(bit) = point {
+                bits |= (bit as u8) << (7 - count);
+                count += 1;
+                if count < 8 {
+                    continue;
+                }
+            }
+            if count > 0 {
+                // a full run ends at this point, any other run just before it
+                let end = match point {
+                    Point::Bit(_) => basex + x + 1,
+                    _ => basex + x,
+                };
+                let start = end - count;
+                unsafe { framebuffer.draw_bits(start, basey + y, bits, count) };
+                (bits, count) = (0, 0);
+            }
+            match point {
+                Point::Bit(_) => {}
+                Point::Coverage(cov) => unsafe {
+                    framebuffer.draw_alpha(basex + x, basey + y, cov)
+                },
+                Point::SubpixelCoverage(r, g, b) => unsafe {
+                    framebuffer.draw_subpixel(basex + x, basey + y, r, g, b)
+                },
+            };
+        }
+        if count > 0 {
+            unsafe { framebuffer.draw_bits(basex + width - count, basey + y, bits, count) };
+        }
+    }
+    Rect::new(basex, basey, width, height)
+}
+
 impl<'a, P: Pixel, F: Font> core::fmt::Write for Fbterm<'a, P, F> {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
         self.print(s);
@@ -267,3 +562,93 @@ impl<'a, P: Pixel, F: Font> core::fmt::Write for Fbterm<'a, P, F> {
 // FIXME: Really safe?
 unsafe impl<'a, P: Pixel, F: Font> Send for Fbterm<'a, P, F> {}
 unsafe impl<'a, P: Pixel, F: Font> Sync for Fbterm<'a, P, F> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fb::gray_fb;
+
+    /// `#` as a full 4x4 cell
+    static FONT: BitmapFont = BitmapFont::new(&[0xf0; 4], 4, 4).with_table(&[('#', 0)]);
+
+    fn filled(buffer: &[u8]) -> [bool; 4] {
+        let mut cells = [false; 4];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            let (x, y) = (i % 2 * 4, i / 2 * 4);
+            *cell = buffer[y * 8 + x] != 0;
+        }
+        cells
+    }
+
+    #[test]
+    fn bit_runs_end_before_coverage() {
+        let mut buffer = [0u8; 4];
+        let mut fb = gray_fb(&mut buffer, 4, 1);
+        draw_points(&mut fb, 0, 0, 4, 1, |x, _| match x {
+            0 => Point::Bit(true),
+            1 => Point::Bit(false),
+            2 => Point::Coverage(0),
+            _ => Point::Bit(true),
+        });
+        assert_eq!(fb.front(), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn glyph_ending_at_edge_fits() {
+        let mut buffer = [0u8; 8 * 8];
+        let fb = gray_fb(&mut buffer, 8, 8);
+        let mut term = Fbterm::new(fb, FONT);
+        term.clear();
+        assert_eq!((term.columns(), term.rows()), (2, 2));
+        term.print("##");
+        assert_eq!(filled(term.framebuffer.front()), [true, true, false, false]);
+        term.print("#");
+        assert_eq!(filled(term.framebuffer.front()), [true, true, true, false]);
+        term.print("#");
+        assert_eq!(filled(term.framebuffer.front()), [true, true, true, true]);
+        term.print("#");
+        assert_eq!(filled(term.framebuffer.front()), [true, true, true, false]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn relayout_reflows_wrapped_lines() {
+        let mut buffer = [0u8; 8 * 8];
+        let fb = gray_fb(&mut buffer, 8, 8);
+        let mut term = Fbterm::new(fb, FONT);
+        term.clear();
+        term.print("###");
+        let lengths = |term: &Fbterm<Gray8, BitmapFont>| {
+            term.lines().iter().map(Vec::len).collect::<Vec<_>>()
+        };
+        assert_eq!(lengths(&term), [2, 1]);
+        // `#` 2 pixels wide, 4 to a row
+        term.set_font(BitmapFont::new(&[0xc0; 4], 2, 4).with_table(&[('#', 0)]));
+        assert_eq!(lengths(&term), [3]);
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    #[test]
+    fn relayout_keeps_cells() {
+        let mut buffer = [0u8; 8 * 8];
+        let fb = gray_fb(&mut buffer, 8, 8);
+        let mut cells = [Cell {
+            c: ' ',
+            foreground: Gray8::new(0, 0, 0, 0),
+            background: Gray8::new(0, 0, 0, 0),
+            style: Style::Regular,
+        }; 4];
+        let mut term = Fbterm::new(fb, FONT);
+        term.set_cells(&mut cells);
+        term.clear();
+        term.print("#\n");
+        term.framebuffer
+            .set_foreground(Gray8::new(128, 128, 128, 255));
+        term.print("##\u{08}");
+        term.relayout();
+        let front = term.framebuffer.front();
+        assert_eq!((front[0], front[4 * 8], front[4 * 8 + 4]), (255, 128, 0));
+        term.print("#");
+        assert_eq!(term.framebuffer.front()[4 * 8 + 4], 128);
+    }
+}
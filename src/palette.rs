@@ -0,0 +1,112 @@
+/// 256 colors of an indexed color framebuffer
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [(u8, u8, u8); 256],
+}
+
+impl Palette {
+    /// The xterm 256 color palette: 16 ANSI colors, a 6x6x6 color cube and 24 grays
+    pub const fn xterm() -> Palette {
+        let mut colors = [(0, 0, 0); 256];
+        let mut i = 0;
+        while i < 256 {
+            colors[i] = xterm_color(i as u8);
+            i += 1;
+        }
+        Palette { colors }
+    }
+
+    #[inline]
+    pub fn get(&self, index: u8) -> (u8, u8, u8) {
+        self.colors[index as usize]
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: u8, color: (u8, u8, u8)) {
+        self.colors[index as usize] = color
+    }
+
+    /// Index of the entry closest to the color
+    pub fn nearest(&self, r: u8, g: u8, b: u8) -> u8 {
+        let mut best = (0, u32::MAX);
+        for (i, &color) in self.colors.iter().enumerate() {
+            let distance = distance((r, g, b), color);
+            if distance < best.1 {
+                best = (i as u8, distance);
+            }
+        }
+        best.0
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::xterm()
+    }
+}
+
+/// Access to the palette of the display hardware (VGA DAC, LCD controller lookup table)
+pub trait PaletteHook {
+    fn load(&mut self, palette: &Palette);
+    fn read(&mut self) -> Palette;
+}
+
+const ANSI: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+pub(crate) const fn xterm_color(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI[index as usize],
+        16..=231 => {
+            let i = (index - 16) as usize;
+            (CUBE[i / 36], CUBE[i / 6 % 6], CUBE[i % 6])
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
+
+/// Closest xterm color cube or gray entry, without searching the palette
+pub(crate) fn xterm_nearest(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (v as usize - 35) / 40,
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (16 + 36 * ri + 6 * gi + bi) as u8;
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance((r, g, b), xterm_color(gray)) < distance((r, g, b), xterm_color(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Squared distance between two colors, weighted by how sensitive the eye is to each channel
+#[inline]
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    3 * d(a.0, b.0) + 4 * d(a.1, b.1) + 2 * d(a.2, b.2)
+}
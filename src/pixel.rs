@@ -1,12 +1,41 @@
+use crate::palette::{xterm_color, xterm_nearest};
+
 pub trait Pixel: Sized + Copy + Clone {
     fn new(r: u8, g: u8, b: u8, a: u8) -> Self;
+    /// Bytes per pixel in memory, which must be overridden if the type has padding
     fn size() -> usize {
         core::mem::size_of::<Self>()
     }
+    /// Bits per pixel in memory. Pixels of 1, 2 or 4 bits share bytes, and are
+    /// written to and read from the low bits of the byte at `ptr`.
+    fn bits() -> usize {
+        Self::size() * 8
+    }
+    /// Alignment of the pointers given to `write_volatile` and `read_volatile`,
+    /// which should be overridden to 1 if they access single bytes
+    fn align() -> usize {
+        core::mem::align_of::<Self>()
+    }
+    /// Pixel drawing palette entry `index`, `None` for direct color pixels
+    fn from_index(_index: u8) -> Option<Self> {
+        None
+    }
+    /// Palette entry drawn by the pixel, `None` for direct color pixels
+    fn index(&self) -> Option<u8> {
+        None
+    }
     fn get(&self) -> (u8, u8, u8, u8);
+    /// Store the pixel at `ptr`
+    ///
+    /// # Safety
+    /// `ptr` must be valid for `size()` bytes and aligned to `align()`
     unsafe fn write_volatile(&self, ptr: *mut u8) {
         (ptr as *mut Self).write_volatile(*self)
     }
+    /// Load the pixel at `ptr`
+    ///
+    /// # Safety
+    /// Same as `write_volatile`
     unsafe fn read_volatile(ptr: *mut u8) -> Self {
         (ptr as *mut Self).read_volatile()
     }
@@ -30,3 +59,225 @@ impl Pixel for RGBA8888 {
         (self.r, self.g, self.b, self.a)
     }
 }
+
+/// 16 bits pixel with 5 bits of red, 6 of green and 5 of blue from the most
+/// significant bit, stored little endian (SPI panels)
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct RGB565(pub u16);
+
+impl Pixel for RGB565 {
+    fn new(r: u8, g: u8, b: u8, _a: u8) -> Self {
+        RGB565((r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3)
+    }
+    fn get(&self) -> (u8, u8, u8, u8) {
+        let (r, g, b) = (self.0 >> 11, (self.0 >> 5) & 0x3f, self.0 & 0x1f);
+        (
+            expand(r as u8, 5),
+            expand(g as u8, 6),
+            expand(b as u8, 5),
+            255,
+        )
+    }
+    fn align() -> usize {
+        1
+    }
+    unsafe fn write_volatile(&self, ptr: *mut u8) {
+        write_bytes(ptr, self.0.to_le_bytes())
+    }
+    unsafe fn read_volatile(ptr: *mut u8) -> Self {
+        RGB565(u16::from_le_bytes(read_bytes(ptr)))
+    }
+}
+
+/// 16 bits pixel with an unused bit, then 5 bits of red, green and blue,
+/// stored little endian
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct RGB555(pub u16);
+
+impl Pixel for RGB555 {
+    fn new(r: u8, g: u8, b: u8, _a: u8) -> Self {
+        RGB555((r as u16 >> 3) << 10 | (g as u16 >> 3) << 5 | b as u16 >> 3)
+    }
+    fn get(&self) -> (u8, u8, u8, u8) {
+        let (r, g, b) = ((self.0 >> 10) & 0x1f, (self.0 >> 5) & 0x1f, self.0 & 0x1f);
+        (
+            expand(r as u8, 5),
+            expand(g as u8, 5),
+            expand(b as u8, 5),
+            255,
+        )
+    }
+    fn align() -> usize {
+        1
+    }
+    unsafe fn write_volatile(&self, ptr: *mut u8) {
+        write_bytes(ptr, self.0.to_le_bytes())
+    }
+    unsafe fn read_volatile(ptr: *mut u8) -> Self {
+        RGB555(u16::from_le_bytes(read_bytes(ptr)))
+    }
+}
+
+/// 24 bits pixel stored as blue, green then red bytes, with no padding
+/// (VESA 24 bits modes)
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct BGR888 {
+    pub b: u8,
+    pub g: u8,
+    pub r: u8,
+}
+
+impl Pixel for BGR888 {
+    fn new(r: u8, g: u8, b: u8, _a: u8) -> Self {
+        BGR888 { b, g, r }
+    }
+    fn get(&self) -> (u8, u8, u8, u8) {
+        (self.r, self.g, self.b, 255)
+    }
+    fn size() -> usize {
+        3
+    }
+    unsafe fn write_volatile(&self, ptr: *mut u8) {
+        write_bytes(ptr, [self.b, self.g, self.r])
+    }
+    unsafe fn read_volatile(ptr: *mut u8) -> Self {
+        let [b, g, r] = read_bytes(ptr);
+        BGR888 { b, g, r }
+    }
+}
+
+/// 32 bits pixel with an unused byte then red, green and blue from the most
+/// significant byte, stored little endian (Linux fbdev)
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct XRGB8888 {
+    pub b: u8,
+    pub g: u8,
+    pub r: u8,
+    pub x: u8,
+}
+
+impl Pixel for XRGB8888 {
+    fn new(r: u8, g: u8, b: u8, _a: u8) -> Self {
+        XRGB8888 { b, g, r, x: 0 }
+    }
+    fn get(&self) -> (u8, u8, u8, u8) {
+        (self.r, self.g, self.b, 255)
+    }
+}
+
+/// 32 bits pixel with blue, green and red from the most significant byte then
+/// an unused byte, stored little endian
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct BGRX8888 {
+    pub x: u8,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Pixel for BGRX8888 {
+    fn new(r: u8, g: u8, b: u8, _a: u8) -> Self {
+        BGRX8888 { x: 0, r, g, b }
+    }
+    fn get(&self) -> (u8, u8, u8, u8) {
+        (self.r, self.g, self.b, 255)
+    }
+}
+
+/// Entry of a 256 color palette. Colors are converted with the xterm palette,
+/// `Framebuffer::set_palette` telling the framebuffer about another one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Indexed(pub u8);
+
+impl Pixel for Indexed {
+    fn new(r: u8, g: u8, b: u8, _a: u8) -> Self {
+        Indexed(xterm_nearest(r, g, b))
+    }
+    fn get(&self) -> (u8, u8, u8, u8) {
+        let (r, g, b) = xterm_color(self.0);
+        (r, g, b, 255)
+    }
+    fn from_index(index: u8) -> Option<Self> {
+        Some(Indexed(index))
+    }
+    fn index(&self) -> Option<u8> {
+        Some(self.0)
+    }
+}
+
+/// Gray level of `BITS` (1, 2, 4 or 8) bits, 0 being black. Narrower pixels are
+/// packed several to a byte (SSD1306 and e-paper panels for 1 bit).
+///
+/// Other widths don't compile:
+///
+/// ```compile_fail
+/// use fbterm::{Gray, Pixel};
+/// let _ = Gray::<3>::new(255, 255, 255, 255);
+/// ```
+#[derive(Debug, Copy, Clone)]
+#[repr(transparent)]
+pub struct Gray<const BITS: usize>(pub u8);
+
+impl<const BITS: usize> Gray<BITS> {
+    const VALID: () = assert!(
+        matches!(BITS, 1 | 2 | 4 | 8),
+        "Gray pixels are 1, 2, 4 or 8 bits"
+    );
+}
+
+pub type Gray1 = Gray<1>;
+pub type Gray2 = Gray<2>;
+pub type Gray4 = Gray<4>;
+pub type Gray8 = Gray<8>;
+
+impl<const BITS: usize> Pixel for Gray<BITS> {
+    fn new(r: u8, g: u8, b: u8, _a: u8) -> Self {
+        let () = Self::VALID;
+        let luma = (r as u32 * 77 + g as u32 * 150 + b as u32 * 29) >> 8;
+        Gray((luma >> (8 - BITS)) as u8)
+    }
+    fn get(&self) -> (u8, u8, u8, u8) {
+        let () = Self::VALID;
+        let level = (self.0 as u32 * 255 / ((1 << BITS) - 1)) as u8;
+        (level, level, level, 255)
+    }
+    fn size() -> usize {
+        1
+    }
+    fn bits() -> usize {
+        let () = Self::VALID;
+        BITS
+    }
+    unsafe fn read_volatile(ptr: *mut u8) -> Self {
+        Gray(ptr.read_volatile() & ((1u16 << BITS) - 1) as u8)
+    }
+}
+
+/// Scale a channel of `bits` bits to 8 bits
+#[inline]
+fn expand(value: u8, bits: u32) -> u8 {
+    (value << (8 - bits)) | (value >> (2 * bits - 8))
+}
+
+/// Write `bytes` one at a time, so `ptr` doesn't need to be aligned
+#[inline]
+unsafe fn write_bytes<const N: usize>(ptr: *mut u8, bytes: [u8; N]) {
+    for (i, byte) in bytes.iter().enumerate() {
+        ptr.add(i).write_volatile(*byte)
+    }
+}
+
+#[inline]
+unsafe fn read_bytes<const N: usize>(ptr: *mut u8) -> [u8; N] {
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = ptr.add(i).read_volatile()
+    }
+    bytes
+}